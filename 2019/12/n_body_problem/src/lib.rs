@@ -1,6 +1,4 @@
-extern crate num_integer;
-
-use num_integer::lcm;
+use number_theory::lcm;
 use std::ops::Add;
 
 #[derive(Debug)]
@@ -172,7 +170,7 @@ pub fn find_repeat(moons: &mut[Moon; 4]) -> u128 {
     let z = first_repeat_one(&mut pz, &mut vz, &pzi, &vzi);
 
     println!("x: {}, y: {}, z: {}", x, y, z);
-    lcm(x, lcm(y, z))
+    lcm(vec![x, y, z])
 }
 
 fn first_repeat_one(p: &mut [i32], v: &mut [i32], pi: &[i32], vi: &[i32]) -> u128 {
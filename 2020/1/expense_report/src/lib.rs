@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+/// Finds every distinct combination of `k` entries in `nums` that add up to `target`.
+///
+/// Each solution is returned in ascending order, and the solutions themselves are
+/// sorted, so a value that appears twice in the input can be used twice, but the
+/// same combination of values is never reported more than once.
+pub fn k_sum(nums: &[i32], k: usize, target: i32) -> Vec<Vec<i32>> {
+    let target = target as i64;
+    let mut solutions = match k {
+        0 => {
+            if target == 0 {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            }
+        }
+        1 => {
+            if nums.iter().any(|&n| n as i64 == target) {
+                vec![vec![target as i32]]
+            } else {
+                Vec::new()
+            }
+        }
+        2 => two_sum(nums, target),
+        _ => {
+            let mut sorted = nums.to_vec();
+            sorted.sort_unstable();
+            let mut solutions = Vec::new();
+            let mut prefix = Vec::with_capacity(k);
+            sorted_k_sum(&sorted, k, target, &mut prefix, &mut solutions);
+            solutions
+        }
+    };
+
+    solutions.sort();
+    solutions
+}
+
+/// Finds the distinct pairs summing to `target` with a single hashed pass over the counts.
+fn two_sum(nums: &[i32], target: i64) -> Vec<Vec<i32>> {
+    let mut counts = HashMap::new();
+    for &n in nums {
+        *counts.entry(n as i64).or_insert(0) += 1;
+    }
+
+    let mut solutions = Vec::new();
+    for (&a, &count) in &counts {
+        let b = target - a;
+        if (a < b && counts.contains_key(&b)) || (a == b && count >= 2) {
+            solutions.push(vec![a as i32, b as i32]);
+        }
+    }
+
+    solutions
+}
+
+/// Fixes one value at a time from the sorted slice and finishes each candidate with a
+/// two-pointer scan once only two values are left to choose.
+fn sorted_k_sum(
+    sorted: &[i32],
+    k: usize,
+    target: i64,
+    prefix: &mut Vec<i32>,
+    solutions: &mut Vec<Vec<i32>>,
+) {
+    if sorted.len() < k {
+        return;
+    }
+
+    if k == 2 {
+        let mut lo = 0;
+        let mut hi = sorted.len() - 1;
+        while lo < hi {
+            let sum = sorted[lo] as i64 + sorted[hi] as i64;
+            if sum < target {
+                lo += 1;
+            } else if sum > target {
+                hi -= 1;
+            } else {
+                let mut solution = prefix.clone();
+                solution.push(sorted[lo]);
+                solution.push(sorted[hi]);
+                solutions.push(solution);
+
+                let (a, b) = (sorted[lo], sorted[hi]);
+                while lo < hi && sorted[lo] == a {
+                    lo += 1;
+                }
+                while lo < hi && sorted[hi] == b {
+                    hi -= 1;
+                }
+            }
+        }
+        return;
+    }
+
+    for i in 0..=sorted.len() - k {
+        if i > 0 && sorted[i] == sorted[i - 1] {
+            continue;
+        }
+
+        prefix.push(sorted[i]);
+        sorted_k_sum(
+            &sorted[i + 1..],
+            k - 1,
+            target - sorted[i] as i64,
+            prefix,
+            solutions,
+        );
+        prefix.pop();
+    }
+}
@@ -1,3 +1,4 @@
+use expense_report::k_sum;
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
@@ -22,30 +23,29 @@ fn parse_file(path: &str) -> Vec<i32> {
     nums
 }
 
-fn part1(nums: &Vec<i32>) {
-    for &i in nums {
-        for &j in nums {
-            if i + j == 2020 {
-                println!("The values are {} and {}, result is {}", i, j, i * j);
-            }
-        }
+fn part1(nums: &[i32]) {
+    for solution in k_sum(nums, 2, 2020) {
+        println!(
+            "The values are {} and {}, result is {}",
+            solution[0],
+            solution[1],
+            product(&solution)
+        );
     }
 }
 
-fn part2(nums: &Vec<i32>) {
-    for &i in nums {
-        for &j in nums {
-            for &k in nums {
-                if i + j + k == 2020 {
-                    println!(
-                        "The values are {}, {}, and {}, result is {}",
-                        i,
-                        j,
-                        k,
-                        i * j * k
-                    );
-                }
-            }
-        }
+fn part2(nums: &[i32]) {
+    for solution in k_sum(nums, 3, 2020) {
+        println!(
+            "The values are {}, {}, and {}, result is {}",
+            solution[0],
+            solution[1],
+            solution[2],
+            product(&solution)
+        );
     }
 }
+
+fn product(values: &[i32]) -> i64 {
+    values.iter().map(|&v| v as i64).product()
+}
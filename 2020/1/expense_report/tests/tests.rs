@@ -0,0 +1,34 @@
+use expense_report::*;
+
+const EXAMPLE: [i32; 6] = [1721, 979, 366, 299, 675, 1456];
+
+#[test]
+fn two_entries() {
+    assert_eq!(vec![vec![299, 1721]], k_sum(&EXAMPLE, 2, 2020));
+}
+
+#[test]
+fn three_entries() {
+    assert_eq!(vec![vec![366, 675, 979]], k_sum(&EXAMPLE, 3, 2020));
+}
+
+#[test]
+fn duplicates_are_used_once_each() {
+    assert_eq!(vec![vec![1010, 1010]], k_sum(&[1010, 1010, 1010], 2, 2020));
+    assert_eq!(Vec::<Vec<i32>>::new(), k_sum(&[1010, 5], 2, 2020));
+}
+
+#[test]
+fn all_distinct_solutions() {
+    assert_eq!(
+        vec![vec![-2, -1, 1, 2], vec![-2, 0, 0, 2], vec![-1, 0, 0, 1]],
+        k_sum(&[1, 0, -1, 0, -2, 2], 4, 0)
+    );
+}
+
+#[test]
+fn trivial_sizes() {
+    assert_eq!(vec![Vec::<i32>::new()], k_sum(&EXAMPLE, 0, 0));
+    assert_eq!(vec![vec![979]], k_sum(&EXAMPLE, 1, 979));
+    assert_eq!(Vec::<Vec<i32>>::new(), k_sum(&EXAMPLE, 7, 2020));
+}
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use num_bigint::BigUint;
+use std::collections::BTreeMap;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Error};
@@ -10,65 +11,181 @@ fn main() -> Result<(), Error> {
         .lines()
         .map(|line| line.unwrap().parse::<usize>().unwrap())
         .collect();
-    println!("The number of combos is {}", combos(&input));
+
+    let gaps: Vec<usize> = match args.iter().position(|a| a == "--gaps") {
+        Some(i) => args[i + 1].split(',').map(|g| g.parse().unwrap()).collect(),
+        None => vec![1, 2, 3],
+    };
+    let chain = Chain::new(&input, &gaps);
+
+    match chain.distribution() {
+        Ok(distribution) => {
+            for (gap, count) in distribution {
+                println!("{} differences of {}", count, gap);
+            }
+        }
+        Err((from, to)) => println!("Can't use every adapter: {} to {} isn't allowed", from, to),
+    }
+    println!("The number of combos is {}", chain.arrangements());
+
+    if let Some(i) = args.iter().position(|a| a == "--show") {
+        let count = args[i + 1].parse().unwrap();
+        for arrangement in chain.iter_arrangements().take(count) {
+            println!("{:?}", arrangement);
+        }
+    }
 
     Ok(())
 }
 
-pub fn ratings(adapters: &[usize]) -> usize {
-    let mut sorted = adapters.to_vec();
-    sorted.sort();
-
-    let mut ones = 0;
-    let mut threes = 1;
-    let mut prev = 0;
-
-    for x in sorted {
-        match x - prev {
-            1 => ones += 1,
-            2 => {}
-            3 => threes += 1,
-            _ => panic!(),
+/// The outlet, adapters and device in joltage order, along with the differences in
+/// joltage that an adapter can accept. The device is rated for the largest allowed
+/// difference above the highest adapter.
+pub struct Chain {
+    joltages: Vec<usize>,
+    allowed: Vec<bool>,
+}
+
+impl Chain {
+    pub fn new(adapters: &[usize], gaps: &[usize]) -> Chain {
+        let max_gap = gaps.iter().copied().max().unwrap_or(0);
+        let mut allowed = vec![false; max_gap + 1];
+        for &gap in gaps {
+            allowed[gap] = true;
+        }
+
+        let mut joltages = adapters.to_vec();
+        joltages.sort_unstable();
+        let device = joltages.last().copied().unwrap_or(0) + max_gap;
+        joltages.insert(0, 0);
+        joltages.push(device);
+
+        Chain { joltages, allowed }
+    }
+
+    fn max_gap(&self) -> usize {
+        self.allowed.len() - 1
+    }
+
+    fn fits(&self, from: usize, to: usize) -> bool {
+        let gap = self.joltages[to] - self.joltages[from];
+        gap <= self.max_gap() && self.allowed[gap]
+    }
+
+    /// How many times each difference in joltage occurs when every adapter is used, or the
+    /// first pair of joltages whose difference isn't allowed.
+    pub fn distribution(&self) -> Result<BTreeMap<usize, usize>, (usize, usize)> {
+        let mut distribution = BTreeMap::new();
+        for i in 1..self.joltages.len() {
+            if !self.fits(i - 1, i) {
+                return Err((self.joltages[i - 1], self.joltages[i]));
+            }
+            *distribution
+                .entry(self.joltages[i] - self.joltages[i - 1])
+                .or_insert(0) += 1;
+        }
+
+        Ok(distribution)
+    }
+
+    /// The number of distinct ways to connect the outlet to the device.
+    pub fn arrangements(&self) -> BigUint {
+        let mut ways = vec![BigUint::from(0u32); self.joltages.len()];
+        ways[0] = BigUint::from(1u32);
+        for to in 1..self.joltages.len() {
+            let mut total = BigUint::from(0u32);
+            for from in (0..to).rev() {
+                if self.joltages[to] - self.joltages[from] > self.max_gap() {
+                    break;
+                }
+                if self.fits(from, to) {
+                    total += &ways[from];
+                }
+            }
+            ways[to] = total;
+        }
+
+        ways.pop().unwrap()
+    }
+
+    /// Lazily lists every arrangement as the adapters it uses, in joltage order.
+    pub fn iter_arrangements(&self) -> Arrangements<'_> {
+        let end = self.joltages.len() - 1;
+        let mut can_finish = vec![false; self.joltages.len()];
+        can_finish[end] = true;
+        for from in (0..end).rev() {
+            can_finish[from] = (from + 1..=end)
+                .take_while(|&to| self.joltages[to] - self.joltages[from] <= self.max_gap())
+                .any(|to| can_finish[to] && self.fits(from, to));
+        }
+
+        let (path, next) = if can_finish[0] {
+            (vec![0], vec![1])
+        } else {
+            (Vec::new(), Vec::new())
         };
-        prev = x;
+        Arrangements {
+            chain: self,
+            can_finish,
+            path,
+            next,
+        }
     }
+}
 
-    ones * threes
+/// A depth-first walk over the arrangements that never steps onto an adapter from which
+/// the device can't be reached, so every step makes progress towards the next result.
+pub struct Arrangements<'a> {
+    chain: &'a Chain,
+    can_finish: Vec<bool>,
+    path: Vec<usize>,
+    next: Vec<usize>,
 }
 
-fn paths(index: usize, adapters: &[usize], knownpaths: &mut HashMap<usize, usize>) -> usize {
-    match knownpaths.get(&index) {
-        Some(paths) => *paths,
-        None => {
-            let val = match index {
-                0 => 1,
-                1 => 1,
-                x => {
-                    let mut v = 0;
-                    for y in 1..4 {
-                        if x >= y && adapters[x-y] - adapters[x] <= 3 {
-                            v += paths(x-y, adapters, knownpaths);
-                        }
-                    }
-                    v
-                }
-            };
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<usize>;
 
-            knownpaths.insert(index, val);
-            val
+    fn next(&mut self) -> Option<Vec<usize>> {
+        let joltages = &self.chain.joltages;
+        let end = joltages.len() - 1;
+        loop {
+            let current = *self.path.last()?;
+            if current == end {
+                let arrangement = self.path[1..self.path.len() - 1]
+                    .iter()
+                    .map(|&i| joltages[i])
+                    .collect();
+                self.path.pop();
+                self.next.pop();
+                return Some(arrangement);
+            }
+
+            let depth = self.path.len() - 1;
+            let candidate = self.next[depth];
+            if candidate <= end && joltages[candidate] - joltages[current] <= self.chain.max_gap() {
+                self.next[depth] += 1;
+                if self.can_finish[candidate] && self.chain.fits(current, candidate) {
+                    self.path.push(candidate);
+                    self.next.push(candidate + 1);
+                }
+            } else {
+                self.path.pop();
+                self.next.pop();
+            }
         }
     }
 }
 
-pub fn combos(adapters: &[usize]) -> usize {
-    let mut sorted = adapters.to_vec();
-    sorted.push(0);
-    sorted.push(sorted.iter().max().unwrap() + 3);
-    sorted.sort();
-    sorted.reverse();
+pub fn ratings(adapters: &[usize]) -> usize {
+    let distribution = Chain::new(adapters, &[1, 2, 3])
+        .distribution()
+        .expect("every adapter should fit");
+
+    distribution.get(&1).unwrap_or(&0) * distribution.get(&3).unwrap_or(&0)
+}
 
-    let mut knownpaths: HashMap<usize, usize> = HashMap::new();
-    paths(sorted.len() - 1, &sorted, &mut knownpaths)
+pub fn combos(adapters: &[usize]) -> BigUint {
+    Chain::new(adapters, &[1, 2, 3]).arrangements()
 }
 
 #[cfg(test)]
@@ -93,13 +210,13 @@ mod tests {
 
     #[test]
     fn example1_part2() {
-        assert_eq!(8, combos(&[16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4,]));
+        assert_eq!(BigUint::from(8u32), combos(&[16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4,]));
     }
 
     #[test]
     fn example2_part2() {
         assert_eq!(
-            19208,
+            BigUint::from(19208u32),
             combos(&[
                 28, 33, 18, 42, 31, 14, 46, 20, 48, 47,
                 24, 23, 49, 45, 19, 38, 39, 11, 1, 32,
@@ -108,4 +225,33 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn enumerate_arrangements() {
+        let chain = Chain::new(&[16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4], &[1, 2, 3]);
+        let all: Vec<Vec<usize>> = chain.iter_arrangements().collect();
+        assert_eq!(8, all.len());
+        assert_eq!(vec![1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19], all[0]);
+        assert_eq!(vec![1, 4, 7, 10, 12, 15, 16, 19], all[7]);
+    }
+
+    #[test]
+    fn other_gaps() {
+        let chain = Chain::new(&[2, 4, 5, 6, 8], &[2]);
+        assert_eq!(Err((4, 5)), chain.distribution());
+        assert_eq!(BigUint::from(1u32), chain.arrangements());
+        assert_eq!(vec![vec![2, 4, 6, 8]], chain.iter_arrangements().collect::<Vec<_>>());
+
+        let chain = Chain::new(&[2, 4, 6], &[2, 4]);
+        assert_eq!(Ok(BTreeMap::from([(2, 3), (4, 1)])), chain.distribution());
+        assert_eq!(BigUint::from(3u32), chain.arrangements());
+    }
+
+    #[test]
+    fn long_chains_do_not_overflow() {
+        let adapters: Vec<usize> = (1..=200).collect();
+        let ways = Chain::new(&adapters, &[1, 2, 3]).arrangements();
+        assert!(ways > BigUint::from(u128::MAX));
+        assert_eq!(0, Chain::new(&[1, 5], &[1, 2, 3]).iter_arrangements().count());
+    }
 }
@@ -1,4 +1,6 @@
 use std::env;
+use std::fmt;
+use std::fs;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -7,14 +9,28 @@ fn main() -> Result<(), Error> {
     let mut reader = BufReader::new(File::open(&args[1])?);
     let script = Action::parse_script_from_bufread(&mut reader)?;
 
-    let mut ship = Ship::new();
-    for action in script {
-        ship = ship.apply_part2(&action);
+    let semantics = if args.iter().any(|a| a == "--part1") {
+        Semantics::Heading
+    } else {
+        Semantics::Waypoint
+    };
+    let angles = if args.iter().any(|a| a == "--any-angle") {
+        Angles::Arbitrary
+    } else {
+        Angles::RightAngles
+    };
+    let trajectory = Trajectory::record(&script, semantics, angles);
+
+    if let Some(i) = args.iter().position(|a| a == "--csv") {
+        fs::write(&args[i + 1], trajectory.to_csv())?;
+    }
+    if let Some(i) = args.iter().position(|a| a == "--svg") {
+        fs::write(&args[i + 1], trajectory.to_svg())?;
     }
 
     println!(
         "The ship is at a manhattan distance of {}",
-        ship.manhattan_distance()
+        trajectory.manhattan_distance()
     );
 
     Ok(())
@@ -40,6 +56,7 @@ impl From<std::num::ParseIntError> for Error {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Action {
     North(isize),
     South(isize),
@@ -85,12 +102,32 @@ impl Action {
     }
 }
 
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::North(val) => write!(f, "N{}", val),
+            Action::South(val) => write!(f, "S{}", val),
+            Action::East(val) => write!(f, "E{}", val),
+            Action::West(val) => write!(f, "W{}", val),
+            Action::Left(val) => write!(f, "L{}", val),
+            Action::Right(val) => write!(f, "R{}", val),
+            Action::Forward(val) => write!(f, "F{}", val),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct WayPoint {
     x: isize,
     y: isize,
 }
 
+impl Default for WayPoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WayPoint {
     pub fn new() -> Self {
         Self { x: 10, y: 1 }
@@ -104,6 +141,12 @@ pub struct Ship {
     waypoint: WayPoint,
 }
 
+impl Default for Ship {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Ship {
     pub fn new() -> Self {
         Self {
@@ -226,11 +269,262 @@ impl Ship {
         }
     }
 
+    pub fn apply_with(&self, action: &Action, semantics: Semantics) -> Self {
+        match semantics {
+            Semantics::Heading => self.apply(action),
+            Semantics::Waypoint => self.apply_part2(action),
+        }
+    }
+
     pub fn manhattan_distance(&self) -> isize {
         self.x.abs() + self.y.abs()
     }
 }
 
+/// Which puzzle part's meaning the actions have: moving the ship along its heading
+/// (`Ship::apply`), or moving a waypoint that the ship follows (`Ship::apply_part2`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Semantics {
+    Heading,
+    Waypoint,
+}
+
+/// Whether turns are limited to multiples of 90 degrees, as in the puzzle, or may be any
+/// angle. Arbitrary angles track the ship with floating point positions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Angles {
+    RightAngles,
+    Arbitrary,
+}
+
+/// A ship with floating point coordinates, so that it can turn by any angle and sail in any
+/// direction. Like `Ship`, every action returns a new ship.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FreeShip {
+    x: f64,
+    y: f64,
+    heading: f64,
+    waypoint_x: f64,
+    waypoint_y: f64,
+}
+
+impl Default for FreeShip {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FreeShip {
+    pub fn new() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            heading: 0.0,
+            waypoint_x: 10.0,
+            waypoint_y: 1.0,
+        }
+    }
+
+    fn moved(&self, dx: f64, dy: f64) -> Self {
+        Self {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }
+    }
+
+    fn waypoint_moved(&self, dx: f64, dy: f64) -> Self {
+        Self {
+            waypoint_x: self.waypoint_x + dx,
+            waypoint_y: self.waypoint_y + dy,
+            ..*self
+        }
+    }
+
+    fn turned(&self, degrees: f64, semantics: Semantics) -> Self {
+        match semantics {
+            Semantics::Heading => Self {
+                heading: (self.heading + degrees).rem_euclid(360.0),
+                ..*self
+            },
+            Semantics::Waypoint => {
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                Self {
+                    waypoint_x: cos * self.waypoint_x - sin * self.waypoint_y,
+                    waypoint_y: sin * self.waypoint_x + cos * self.waypoint_y,
+                    ..*self
+                }
+            }
+        }
+    }
+
+    pub fn apply(&self, action: &Action, semantics: Semantics) -> Self {
+        let (dx, dy) = match *action {
+            Action::North(val) => (0.0, val as f64),
+            Action::South(val) => (0.0, -val as f64),
+            Action::East(val) => (val as f64, 0.0),
+            Action::West(val) => (-val as f64, 0.0),
+            Action::Left(val) => return self.turned(val as f64, semantics),
+            Action::Right(val) => return self.turned(-val as f64, semantics),
+            Action::Forward(val) => {
+                let val = val as f64;
+                return match semantics {
+                    Semantics::Heading => {
+                        let (sin, cos) = self.heading.to_radians().sin_cos();
+                        self.moved(val * cos, val * sin)
+                    }
+                    Semantics::Waypoint => {
+                        self.moved(val * self.waypoint_x, val * self.waypoint_y)
+                    }
+                };
+            }
+        };
+
+        match semantics {
+            Semantics::Heading => self.moved(dx, dy),
+            Semantics::Waypoint => self.waypoint_moved(dx, dy),
+        }
+    }
+}
+
+/// Where the ship and its waypoint were after an action. The waypoint is relative to the
+/// ship, as it is in `Ship`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub action: Option<Action>,
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+    pub waypoint_x: f64,
+    pub waypoint_y: f64,
+}
+
+impl Point {
+    fn from_ship(action: Option<Action>, ship: &Ship) -> Self {
+        Self {
+            action,
+            x: ship.x as f64,
+            y: ship.y as f64,
+            heading: ship.heading.rem_euclid(360) as f64,
+            waypoint_x: ship.waypoint.x as f64,
+            waypoint_y: ship.waypoint.y as f64,
+        }
+    }
+
+    fn from_free_ship(action: Option<Action>, ship: &FreeShip) -> Self {
+        Self {
+            action,
+            x: ship.x,
+            y: ship.y,
+            heading: ship.heading,
+            waypoint_x: ship.waypoint_x,
+            waypoint_y: ship.waypoint_y,
+        }
+    }
+}
+
+/// Every state the ship passes through while following a script, starting from where it
+/// sets off.
+pub struct Trajectory {
+    semantics: Semantics,
+    points: Vec<Point>,
+}
+
+impl Trajectory {
+    pub fn record(script: &[Action], semantics: Semantics, angles: Angles) -> Self {
+        let mut points = Vec::with_capacity(script.len() + 1);
+        match angles {
+            Angles::RightAngles => {
+                let mut ship = Ship::new();
+                points.push(Point::from_ship(None, &ship));
+                for action in script {
+                    ship = ship.apply_with(action, semantics);
+                    points.push(Point::from_ship(Some(*action), &ship));
+                }
+            }
+            Angles::Arbitrary => {
+                let mut ship = FreeShip::new();
+                points.push(Point::from_free_ship(None, &ship));
+                for action in script {
+                    ship = ship.apply(action, semantics);
+                    points.push(Point::from_free_ship(Some(*action), &ship));
+                }
+            }
+        }
+
+        Self { semantics, points }
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    pub fn manhattan_distance(&self) -> f64 {
+        let last = self.points.last().unwrap();
+        last.x.abs() + last.y.abs()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,action,x,y,heading,waypoint_x,waypoint_y\n");
+        for (step, p) in self.points.iter().enumerate() {
+            let action = p.action.map(|a| a.to_string()).unwrap_or_default();
+            csv += &format!(
+                "{},{},{},{},{},{},{}\n",
+                step, action, p.x, p.y, p.heading, p.waypoint_x, p.waypoint_y
+            );
+        }
+        csv
+    }
+
+    /// Draws the ship's course as an SVG path with north at the top. When following a
+    /// waypoint, the waypoint's course is drawn as a second, dashed path.
+    pub fn to_svg(&self) -> String {
+        // Subtracting from zero rather than negating keeps `-0` out of the output.
+        let ship: Vec<(f64, f64)> = self.points.iter().map(|p| (p.x, 0.0 - p.y)).collect();
+        let mut paths = vec![(ship, "")];
+        if self.semantics == Semantics::Waypoint {
+            let waypoint = self
+                .points
+                .iter()
+                .map(|p| (p.x + p.waypoint_x, 0.0 - (p.y + p.waypoint_y)))
+                .collect();
+            paths.push((waypoint, " stroke-dasharray=\"4\""));
+        }
+
+        let all = paths.iter().flat_map(|(points, _)| points.iter());
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        for &(x, y) in all {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let margin = ((max_x - min_x).max(max_y - min_y) / 20.0).max(1.0);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+            min_x - margin,
+            min_y - margin,
+            max_x - min_x + 2.0 * margin,
+            max_y - min_y + 2.0 * margin
+        );
+        for (points, style) in paths {
+            let d: Vec<String> = points
+                .iter()
+                .enumerate()
+                .map(|(i, (x, y))| format!("{}{} {}", if i == 0 { "M" } else { "L" }, x, y))
+                .collect();
+            svg += &format!(
+                "  <path d=\"{}\" fill=\"none\" stroke=\"black\" vector-effect=\"non-scaling-stroke\"{}/>\n",
+                d.join(" "),
+                style
+            );
+        }
+        svg += "</svg>\n";
+        svg
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -437,4 +731,68 @@ F11",
         assert_eq!(-10, ship.waypoint.x);
         assert_eq!(0, ship.waypoint.y);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn trajectory() -> Result<(), Error> {
+        let script = Action::parse_script_from_string("F10\nN3\nF7\nR90\nF11")?;
+
+        let trajectory = Trajectory::record(&script, Semantics::Heading, Angles::RightAngles);
+        assert_eq!(6, trajectory.points().len());
+        assert_eq!(25.0, trajectory.manhattan_distance());
+        assert_eq!(
+            "step,action,x,y,heading,waypoint_x,waypoint_y
+0,,0,0,0,10,1
+1,F10,10,0,0,10,1
+2,N3,10,3,0,10,1
+3,F7,17,3,0,10,1
+4,R90,17,3,270,10,1
+5,F11,17,-8,270,10,1
+",
+            trajectory.to_csv()
+        );
+
+        let trajectory = Trajectory::record(&script, Semantics::Waypoint, Angles::RightAngles);
+        assert_eq!(286.0, trajectory.manhattan_distance());
+        assert_eq!((4.0, -10.0), {
+            let p = &trajectory.points()[4];
+            (p.waypoint_x, p.waypoint_y)
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn arbitrary_angles() -> Result<(), Error> {
+        let script = Action::parse_script_from_string("F10\nN3\nF7\nR90\nF11")?;
+        for semantics in [Semantics::Heading, Semantics::Waypoint] {
+            let exact = Trajectory::record(&script, semantics, Angles::RightAngles);
+            let free = Trajectory::record(&script, semantics, Angles::Arbitrary);
+            for (e, f) in exact.points().iter().zip(free.points()) {
+                assert!((e.x - f.x).abs() < 1e-9 && (e.y - f.y).abs() < 1e-9);
+            }
+        }
+
+        let script = Action::parse_script_from_string("R45\nF10")?;
+        let trajectory = Trajectory::record(&script, Semantics::Heading, Angles::Arbitrary);
+        let end = &trajectory.points()[2];
+        assert!((end.x - 50f64.sqrt()).abs() < 1e-9);
+        assert!((end.y + 50f64.sqrt()).abs() < 1e-9);
+        assert_eq!(315.0, end.heading);
+
+        Ok(())
+    }
+
+    #[test]
+    fn svg() -> Result<(), Error> {
+        let script = Action::parse_script_from_string("F10\nN3")?;
+        let svg = Trajectory::record(&script, Semantics::Heading, Angles::RightAngles).to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -4 12 5\">"));
+        assert!(svg.contains("d=\"M0 0 L10 0 L10 -3\""));
+        assert!(!svg.contains("stroke-dasharray"));
+
+        let svg = Trajectory::record(&script, Semantics::Waypoint, Angles::RightAngles).to_svg();
+        assert!(svg.contains("stroke-dasharray"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! Number theory helpers shared between days. Everything is generic over `num_integer`'s
+//! `Integer`, so it works the same for the primitive integer types and for `BigInt`.
+
+pub use num_bigint::{BigInt, BigUint};
+use num_integer::{Integer, Roots};
+use num_traits::Signed;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Returns `(g, x, y)` where `g` is the non-negative greatest common divisor of `a` and `b`,
+/// and `a * x + b * y == g`.
+pub fn extended_gcd<T: Integer + Signed + Clone>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (T::one(), T::zero());
+    let (mut old_t, mut t) = (T::zero(), T::one());
+    while !r.is_zero() {
+        let quotient = old_r.div_floor(&r);
+
+        let next_r = old_r - quotient.clone() * r.clone();
+        old_r = std::mem::replace(&mut r, next_r);
+
+        let next_s = old_s - quotient.clone() * s.clone();
+        old_s = std::mem::replace(&mut s, next_s);
+
+        let next_t = old_t - quotient * t.clone();
+        old_t = std::mem::replace(&mut t, next_t);
+    }
+
+    if old_r.is_negative() {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/// The least common multiple of all of `values`, or one if there are none.
+pub fn lcm<T: Integer + Clone, I: IntoIterator<Item = T>>(values: I) -> T {
+    values.into_iter().fold(T::one(), |acc, v| acc.lcm(&v))
+}
+
+/// The `x` in `0..modulus` with `a * x ≡ 1 (mod modulus)`, if `a` and `modulus` are coprime.
+pub fn mod_inverse<T: Integer + Signed + Clone>(a: T, modulus: T) -> Option<T> {
+    let (g, x, _) = extended_gcd(a, modulus.clone());
+    if g.is_one() {
+        Some(x.mod_floor(&modulus))
+    } else {
+        None
+    }
+}
+
+/// `base` raised to `exponent`, modulo `modulus`, by repeated squaring. `exponent` must not be
+/// negative. Products are worked out in `T` before reducing, so `(modulus - 1)^2` has to fit in
+/// `T`; use a wider type, or `BigInt`, for larger moduli.
+pub fn mod_pow<T: Integer + Clone>(base: T, exponent: T, modulus: T) -> T {
+    let two = T::one() + T::one();
+    let mut result = T::one().mod_floor(&modulus);
+    let mut base = base.mod_floor(&modulus);
+    let mut exponent = exponent;
+    while !exponent.is_zero() {
+        if exponent.is_odd() {
+            result = (result * base.clone()).mod_floor(&modulus);
+        }
+        base = (base.clone() * base).mod_floor(&modulus);
+        exponent = exponent / two.clone();
+    }
+
+    result
+}
+
+/// The congruence at `index` can't be satisfied together with the ones before it.
+#[derive(Debug, PartialEq)]
+pub struct Inconsistent {
+    pub index: usize,
+}
+
+/// Solves the system `x ≡ residue (mod modulus)` for every `(residue, modulus)` pair, giving
+/// `(x, m)` where `m` is the least common multiple of the moduli and `0 <= x < m`. Unlike the
+/// textbook Chinese Remainder Theorem, the moduli don't need to be pairwise coprime: any
+/// two congruences can be merged as long as their residues agree modulo the gcd of their
+/// moduli, and the first one that doesn't is reported.
+pub fn crt<T: Integer + Signed + Clone>(congruences: &[(T, T)]) -> Result<(T, T), Inconsistent> {
+    let mut x = T::zero();
+    let mut m = T::one();
+    for (index, (residue, modulus)) in congruences.iter().enumerate() {
+        let modulus = modulus.abs();
+        let residue = residue.mod_floor(&modulus);
+
+        let (g, p, _) = extended_gcd(m.clone(), modulus.clone());
+        let difference = residue - x.clone();
+        if !difference.is_multiple_of(&g) {
+            return Err(Inconsistent { index });
+        }
+
+        let step = modulus.clone() / g.clone();
+        let k = ((difference / g) * p).mod_floor(&step);
+        x = x + m.clone() * k;
+        m = m * step;
+        x = x.mod_floor(&m);
+    }
+
+    Ok((x, m))
+}
+
+/// The prime factors of `n` with their multiplicities, smallest first, by trial division. Only
+/// quick when `n` has no more than one large prime factor.
+pub fn factorize<T: Integer + Clone>(n: T) -> Vec<(T, u32)> {
+    let mut n = n;
+    let mut factors = Vec::new();
+    let mut p = T::one() + T::one();
+    while p.clone() * p.clone() <= n {
+        let mut count = 0;
+        while n.is_multiple_of(&p) {
+            n = n / p.clone();
+            count += 1;
+        }
+        if count > 0 {
+            factors.push((p.clone(), count));
+        }
+        p = p + T::one();
+    }
+
+    if n > T::one() {
+        factors.push((n, 1));
+    }
+    factors
+}
+
+/// The smallest positive `k` with `base^k ≡ 1 (mod modulus)`, given any multiple `n` of it,
+/// such as the order of the whole group (`modulus - 1` when `modulus` is prime).
+pub fn multiplicative_order<T: Integer + Clone>(base: T, modulus: T, n: T) -> T {
+    let mut order = n.clone();
+    for (p, _) in factorize(n) {
+        while order.is_multiple_of(&p)
+            && mod_pow(base.clone(), order.clone() / p.clone(), modulus.clone()).is_one()
+        {
+            order = order / p.clone();
+        }
+    }
+    order
+}
+
+/// The smallest `x` in `0..bound` with `base^x ≡ target (mod modulus)`, found with
+/// baby-step giant-step in about `2 * sqrt(bound)` multiplications. `base` must be coprime to
+/// `modulus`.
+pub fn baby_step_giant_step<T: Integer + Roots + Clone + Hash>(
+    base: T,
+    target: T,
+    modulus: T,
+    bound: T,
+) -> Option<T> {
+    let base = base.mod_floor(&modulus);
+    let target = target.mod_floor(&modulus);
+    if target == T::one().mod_floor(&modulus) {
+        return Some(T::zero());
+    }
+
+    let mut m = bound.sqrt();
+    if m.clone() * m.clone() < bound {
+        m = m + T::one();
+    }
+
+    // Baby steps: target * base^j for j in 0..m. Later j overwrite earlier ones, which keeps
+    // x = i * m - j as small as possible below.
+    let mut baby = HashMap::new();
+    let mut value = target;
+    let mut j = T::zero();
+    while j < m {
+        baby.insert(value.clone(), j.clone());
+        value = (value * base.clone()).mod_floor(&modulus);
+        j = j + T::one();
+    }
+
+    // Giant steps: base^(i * m) for i in 1..=m. Each i covers x in (i * m - m, i * m], so the
+    // first match is the smallest.
+    let giant = mod_pow(base, m.clone(), modulus.clone());
+    let mut value = giant.clone();
+    let mut i = T::one();
+    while i <= m {
+        if let Some(j) = baby.get(&value) {
+            let x = i * m - j.clone();
+            return if x < bound { Some(x) } else { None };
+        }
+        value = (value * giant.clone()).mod_floor(&modulus);
+        i = i + T::one();
+    }
+
+    None
+}
+
+/// The smallest non-negative `x` with `base^x ≡ target (mod modulus)`, given `n`, a multiple of
+/// the order of `base` such as `modulus - 1` for a prime `modulus`. Pohlig–Hellman splits the
+/// problem into one per prime power dividing the order of `base` and solves those a digit at a
+/// time with [`baby_step_giant_step`], so it's fast whenever `n` has only small prime factors.
+pub fn discrete_log<T: Integer + Signed + Roots + Clone + Hash>(
+    base: T,
+    target: T,
+    modulus: T,
+    n: T,
+) -> Option<T> {
+    let base = base.mod_floor(&modulus);
+    let target = target.mod_floor(&modulus);
+    let pow = |b: &T, e: T| mod_pow(b.clone(), e, modulus.clone());
+
+    // Working in the order of base rather than n means each prime power's subgroup below has
+    // exactly that order, so none of the digits collapse to a trivial subgroup.
+    let order = multiplicative_order(base.clone(), modulus.clone(), n);
+
+    let mut congruences = Vec::new();
+    for (p, e) in factorize(order.clone()) {
+        let prime_power = num_traits::pow(p.clone(), e as usize);
+
+        // Move into the subgroup of order p^e, where x is only known modulo p^e.
+        let cofactor = order.clone() / prime_power.clone();
+        let g = pow(&base, cofactor.clone());
+        let h = pow(&target, cofactor);
+
+        // Find x's base p digits from the bottom up. Raising to p^(e-1-k) sends everything to
+        // the subgroup of order p, where the k-th digit is a discrete log with only p options.
+        let gamma = pow(&g, prime_power.clone() / p.clone());
+        let mut x = T::zero();
+        let mut place = T::one();
+        for k in 0..e {
+            // g^-x is g^(p^e - x), as g^(p^e) is one.
+            let remaining =
+                (pow(&g, prime_power.clone() - x.clone()) * h.clone()).mod_floor(&modulus);
+            let exponent = num_traits::pow(p.clone(), (e - 1 - k) as usize);
+            let digit = baby_step_giant_step(
+                gamma.clone(),
+                pow(&remaining, exponent),
+                modulus.clone(),
+                p.clone(),
+            )?;
+            x = x + digit * place.clone();
+            place = place * p.clone();
+        }
+
+        congruences.push((x, prime_power));
+    }
+
+    // The solution is unique modulo the order of base, so this is the smallest. It still
+    // needs checking, because target might not be a power of base at all.
+    let (x, _) = crt(&congruences).ok()?;
+    if pow(&base, x.clone()) == target {
+        Some(x)
+    } else {
+        None
+    }
+}
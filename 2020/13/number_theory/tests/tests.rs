@@ -0,0 +1,127 @@
+use number_theory::*;
+
+#[test]
+fn extended_gcd_test() {
+    assert_eq!((1, -1, 1), extended_gcd(3, 4));
+    assert_eq!((6, 1, -2), extended_gcd(30, 12));
+    assert_eq!((4, 0, -1), extended_gcd(0, -4));
+}
+
+#[test]
+fn lcm_test() {
+    assert_eq!(4686774924u128, lcm(vec![2028u128, 5898, 4702]));
+    assert_eq!(1, lcm(Vec::<i64>::new()));
+}
+
+#[test]
+fn mod_inverse_test() {
+    assert_eq!(Some(4), mod_inverse(3, 11));
+    assert_eq!(Some(10), mod_inverse(-1, 11));
+    assert_eq!(None, mod_inverse(6, 9));
+}
+
+#[test]
+fn mod_pow_test() {
+    assert_eq!(5764801, mod_pow(7u64, 8, 20201227));
+    assert_eq!(14897079, mod_pow(17807724u64, 8, 20201227));
+    assert_eq!(0, mod_pow(5, 0, 1));
+
+    let big = mod_pow(BigInt::from(2), BigInt::from(1000), BigInt::from(10).pow(30) + 57);
+    assert!(big < BigInt::from(10).pow(30) + 57);
+    assert_eq!(big, BigInt::from(2).modpow(&BigInt::from(1000), &(BigInt::from(10).pow(30) + 57)));
+}
+
+#[test]
+fn crt_coprime() {
+    // The bus schedule "7,13,x,x,59,x,31,19" from 2020 day 13.
+    let buses = [(7, 0), (13, 1), (59, 4), (31, 6), (19, 7)];
+    let congruences: Vec<(i128, i128)> = buses.iter().map(|&(id, offset)| (-offset, id)).collect();
+    assert_eq!(Ok((1068781, 3162341)), crt(&congruences));
+}
+
+#[test]
+fn crt_non_coprime() {
+    assert_eq!(Ok((9, 12)), crt(&[(3, 6), (1, 4)]));
+    assert_eq!(Err(Inconsistent { index: 1 }), crt(&[(3, 6), (2, 4)]));
+    assert_eq!(Ok((0, 1)), crt::<i32>(&[]));
+}
+
+#[test]
+fn crt_big() {
+    let primes = [1_000_000_007i64, 998_244_353, 1_000_000_009, 999_999_937];
+    let congruences: Vec<(BigInt, BigInt)> = primes
+        .iter()
+        .map(|&p| (BigInt::from(p - 1), BigInt::from(p)))
+        .collect();
+    let product = primes.iter().fold(BigInt::from(1), |acc, &p| acc * p);
+    assert_eq!(Ok((product.clone() - 1, product)), crt(&congruences));
+}
+
+#[test]
+fn factorize_test() {
+    assert_eq!(vec![(2, 1), (3, 1), (29, 1), (116099, 1)], factorize(20201226));
+    assert_eq!(vec![(2, 3), (3, 2)], factorize(72));
+    assert_eq!(Vec::<(u32, u32)>::new(), factorize(1));
+}
+
+#[test]
+fn multiplicative_order_test() {
+    assert_eq!(10, multiplicative_order(2, 11, 10));
+    assert_eq!(5, multiplicative_order(3, 11, 10));
+    assert_eq!(1, multiplicative_order(1, 11, 10));
+}
+
+#[test]
+fn baby_step_giant_step_test() {
+    assert_eq!(Some(8), baby_step_giant_step(7u64, 5764801, 20201227, 20201226));
+    assert_eq!(Some(11), baby_step_giant_step(7u64, 17807724, 20201227, 20201226));
+    assert_eq!(Some(0), baby_step_giant_step(7u64, 1, 20201227, 20201226));
+    assert_eq!(Some(8), baby_step_giant_step(2, 3, 11, 10));
+    assert_eq!(None, baby_step_giant_step(2, 3, 11, 8));
+    assert_eq!(None, baby_step_giant_step(3, 2, 11, 10));
+}
+
+#[test]
+fn discrete_log_non_generator() {
+    // 4 has order 6 mod 13, which only covers 2 of the 2^2 in 12.
+    assert_eq!(Some(1), discrete_log(4, 4, 13, 12));
+    assert_eq!(Some(3), discrete_log(4, 12, 13, 12));
+    assert_eq!(None, discrete_log(4, 2, 13, 12));
+
+    for modulus in &[13i64, 17, 31, 37] {
+        for base in 1..*modulus {
+            for target in 1..*modulus {
+                let expected = (0..*modulus).find(|&x| mod_pow(base, x, *modulus) == target);
+                assert_eq!(
+                    expected,
+                    discrete_log(base, target, *modulus, modulus - 1),
+                    "{}^x = {} mod {}",
+                    base,
+                    target,
+                    modulus
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn discrete_log_test() {
+    assert_eq!(Some(8), discrete_log(7i64, 5764801, 20201227, 20201226));
+    assert_eq!(Some(11), discrete_log(7i64, 17807724, 20201227, 20201226));
+    assert_eq!(Some(8), discrete_log(2, 3, 11, 10));
+    assert_eq!(Some(2), discrete_log(4, 5, 11, 10));
+    assert_eq!(None, discrete_log(3, 2, 11, 10));
+    assert_eq!(
+        Some(123456789),
+        discrete_log(5i64, 372224738, 1000000007, 1000000006)
+    );
+
+    // p - 1 = 2^11 * 3^5 * 5^4 * 7^3 * 11^2 * 13 * 17 * 19, far too big for baby-step
+    // giant-step alone, but smooth.
+    let p = 54205282010880001i128;
+    assert_eq!(
+        Some(31415926535897932),
+        discrete_log(41, 9187964789083531, p, p - 1)
+    );
+}
@@ -1,3 +1,4 @@
+use number_theory::{crt, BigInt};
 use std::io::BufRead;
 
 #[derive(Debug)]
@@ -42,8 +43,10 @@ fn main() -> Result<(), Error> {
         lines.next(); // skip first line
         if let Some(input) = lines.next() {
             let schedule = SchedulePart2::parse(&input?)?;
-            let time = schedule.first_time_chinese_remainder_theorem();
-            println!("The first time to meet the constraint is {}", time);
+            match schedule.first_time_chinese_remainder_theorem() {
+                Some(time) => println!("The first time to meet the constraint is {}", time),
+                None => println!("The buses never meet the constraint"),
+            }
         }
     }
     Ok(())
@@ -127,68 +130,21 @@ impl SchedulePart2 {
                 return cur;
             }
 
-            cur = cur + self.ids_and_offsets[max_index].0;
+            cur += self.ids_and_offsets[max_index].0;
         }
     }
 
-    pub fn first_time_chinese_remainder_theorem(&self) -> i128 {
-        first_time_rec(&self.ids_and_offsets)
-    }
-}
-
-fn first_time_rec(ids_and_offsets: &Vec<(i128, i128)>) -> i128 {
-    println!("Recursing on {:?}", ids_and_offsets);
-    if ids_and_offsets.len() == 1 {
-        return (ids_and_offsets[0].0 - ids_and_offsets[0].1) % ids_and_offsets[0].0;
-    }
-
-    let mut res = Vec::new();
-    for i in 0..ids_and_offsets.len() / 2 {
-        let (n1, a1) = ids_and_offsets[2 * i];
-        let (n2, a2) = ids_and_offsets[2 * i + 1];
-        res.push(reduce(n1, a1, n2, a2));
-    }
+    /// Every bus leaves at `time + offset`, i.e. `time ≡ -offset (mod id)`. Solving that system
+    /// doesn't rely on the bus ids being coprime, and `None` means the buses can never line up.
+    pub fn first_time_chinese_remainder_theorem(&self) -> Option<BigInt> {
+        let congruences: Vec<(BigInt, BigInt)> = self
+            .ids_and_offsets
+            .iter()
+            .map(|&(id, offset)| (BigInt::from(-offset), BigInt::from(id)))
+            .collect();
 
-    if ids_and_offsets.len() % 2 == 1 {
-        res.push(ids_and_offsets[ids_and_offsets.len() - 1]);
+        crt(&congruences).ok().map(|(time, _)| time)
     }
-
-    first_time_rec(&res)
-}
-
-fn reduce(n1: i128, a1: i128, n2: i128, a2: i128) -> (i128, i128) {
-    let (m1, m2) = extended_euclidean_algorithm(n1, n2);
-    let nr = n1 * n2;
-    let ar = (a1 * m2 * n2 + a2 * m1 * n1) % nr;
-
-    println!(
-        "  Reduced a1:{}, n1:{}, a2:{}, n2:{} => nr:{}, ar:{} with m1, m2 = {}, {}",
-        a1, n1, a2, n2, nr, ar, m1, m2
-    );
-    (nr, ar)
-}
-
-fn extended_euclidean_algorithm(a: i128, b: i128) -> (i128, i128) {
-    let (mut old_r, mut r) = (a, b);
-    let (mut old_s, mut s) = (1, 0);
-    let (mut old_t, mut t) = (0, 1);
-    while r != 0 {
-        let quotient = old_r / r;
-
-        let tr = r;
-        r = old_r - quotient * r;
-        old_r = tr;
-
-        let ts = s;
-        s = old_s - quotient * s;
-        old_s = ts;
-
-        let tt = t;
-        t = old_t - quotient * t;
-        old_t = tt;
-    }
-
-    (old_s, old_t)
 }
 
 #[cfg(test)]
@@ -207,48 +163,51 @@ mod tests {
     #[test]
     fn part2_1() -> Result<(), std::num::ParseIntError> {
         let schedule = SchedulePart2::parse("7,13,x,x,59,x,31,19")?;
-        assert_eq!(1068781, schedule.first_time_chinese_remainder_theorem());
+        assert_eq!(Some(BigInt::from(1068781)), schedule.first_time_chinese_remainder_theorem());
         Ok(())
     }
 
     #[test]
     fn part2_2() -> Result<(), std::num::ParseIntError> {
         let schedule = SchedulePart2::parse("17,x,13,19")?;
-        assert_eq!(3417, schedule.first_time_chinese_remainder_theorem());
+        assert_eq!(Some(BigInt::from(3417)), schedule.first_time_chinese_remainder_theorem());
         Ok(())
     }
 
     #[test]
     fn part2_3() -> Result<(), std::num::ParseIntError> {
         let schedule = SchedulePart2::parse("67,7,59,61")?;
-        assert_eq!(754018, schedule.first_time_chinese_remainder_theorem());
+        assert_eq!(Some(BigInt::from(754018)), schedule.first_time_chinese_remainder_theorem());
         Ok(())
     }
 
     #[test]
     fn part2_4() -> Result<(), std::num::ParseIntError> {
         let schedule = SchedulePart2::parse("67,x,7,59,61")?;
-        assert_eq!(779210, schedule.first_time_chinese_remainder_theorem());
+        assert_eq!(Some(BigInt::from(779210)), schedule.first_time_chinese_remainder_theorem());
         Ok(())
     }
 
     #[test]
     fn part2_5() -> Result<(), std::num::ParseIntError> {
         let schedule = SchedulePart2::parse("67,7,x,59,61")?;
-        assert_eq!(1261476, schedule.first_time_chinese_remainder_theorem());
+        assert_eq!(Some(BigInt::from(1261476)), schedule.first_time_chinese_remainder_theorem());
         Ok(())
     }
 
     #[test]
     fn part2_6() -> Result<(), std::num::ParseIntError> {
         let schedule = SchedulePart2::parse("1789,37,47,1889")?;
-        assert_eq!(1202161486, schedule.first_time_chinese_remainder_theorem());
+        assert_eq!(Some(BigInt::from(1202161486)), schedule.first_time_chinese_remainder_theorem());
         Ok(())
     }
 
     #[test]
-    fn test_extended_euclidean_algorithm() {
-        let x = extended_euclidean_algorithm(3, 4);
-        assert_eq!((-1, 1), x);
+    fn part2_not_coprime() -> Result<(), std::num::ParseIntError> {
+        let schedule = SchedulePart2::parse("6,x,4")?;
+        assert_eq!(Some(BigInt::from(6)), schedule.first_time_chinese_remainder_theorem());
+        let schedule = SchedulePart2::parse("6,4")?;
+        assert_eq!(None, schedule.first_time_chinese_remainder_theorem());
+        Ok(())
     }
 }
@@ -5,12 +5,16 @@ fn main() -> Result<(), Error> {
     let input = std::fs::read_to_string(&args[1])?;
 
     let program = Program::parse(&input)?;
-    let mut memory = HashMap::new();
-    program.run_part2(&mut memory);
-    println!(
-        "The answer is: {}",
-        memory.iter().fold(0, |acc, (_, v)| acc + v)
-    );
+    if args.iter().any(|a| a == "--naive") {
+        let mut memory = HashMap::new();
+        program.run_part2(&mut memory);
+        println!(
+            "The answer is: {}",
+            memory.iter().fold(0, |acc, (_, v)| acc + v)
+        );
+    } else {
+        println!("The answer is: {}", program.run_part2_symbolic().sum());
+    }
 
     Ok(())
 }
@@ -46,10 +50,8 @@ impl Program {
     pub fn parse(input: &str) -> Result<Self, Error> {
         let mut instrs = Vec::new();
         for line in input.lines() {
-            if line.starts_with("mask = ") {
-                let mask = &line["mask = ".len()..];
+            if let Some(mask) = line.strip_prefix("mask = ") {
                 instrs.push(Instruction::Mask(String::from(mask)));
-                println!("parsed Instruction::Mask({})", mask);
             } else if line.starts_with("mem[") {
                 let mut addr = 0;
                 for (i, c) in line.char_indices() {
@@ -57,7 +59,6 @@ impl Program {
                         addr = line[4..i].parse()?;
                     } else if c == '=' {
                         let val = line[i + 2..].parse()?;
-                        println!("parsed Instruction::Mem({}, {})", addr, val);
                         instrs.push(Instruction::Mem(addr, val));
                         break;
                     }
@@ -138,6 +139,25 @@ impl Program {
         }
     }
 
+    /// Runs part 2 without expanding floating bits into individual addresses. See
+    /// `FloatingMemory` for how the writes are stored.
+    pub fn run_part2_symbolic(&self) -> FloatingMemory {
+        let mut memory = FloatingMemory::new();
+        let mut cur_mask = "";
+        for instruction in &self.instructions {
+            match instruction {
+                Instruction::Mask(mask) => {
+                    cur_mask = mask;
+                }
+                Instruction::Mem(addr, val) => {
+                    memory.write(Pattern::from_mask(cur_mask, *addr as u64), *val);
+                }
+            }
+        }
+
+        memory
+    }
+
     fn float_bit(bit: usize, addrs: &[String]) -> Vec<String> {
         let mut ret = Vec::new();
         for addr in addrs {
@@ -149,6 +169,107 @@ impl Program {
     }
 }
 
+/// A set of addresses where the bits in `floating` can be either value, and every other bit
+/// is as in `fixed`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pattern {
+    fixed: u64,
+    floating: u64,
+}
+
+impl Pattern {
+    pub fn from_mask(mask: &str, addr: u64) -> Self {
+        let mut ones = 0;
+        let mut floating = 0;
+        for (i, c) in mask.char_indices() {
+            let bit = 1 << (mask.len() - 1 - i);
+            match c {
+                '1' => ones |= bit,
+                'X' => floating |= bit,
+                _ => {}
+            }
+        }
+
+        Self {
+            fixed: (addr | ones) & !floating,
+            floating,
+        }
+    }
+
+    /// The number of addresses that match.
+    pub fn size(&self) -> u128 {
+        1 << self.floating.count_ones()
+    }
+
+    /// Whether any address matches both patterns, which is when they agree on every bit that
+    /// neither of them floats.
+    pub fn overlaps(&self, other: &Pattern) -> bool {
+        (self.fixed ^ other.fixed) & !self.floating & !other.floating == 0
+    }
+
+    /// Splits the addresses in `self` that aren't in `other` into disjoint patterns, one for
+    /// each bit that floats in `self` but is fixed in `other`.
+    pub fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+
+        let mut pieces = Vec::new();
+        let mut rest = *self;
+        let mut splits = self.floating & !other.floating;
+        while splits != 0 {
+            let bit = splits & splits.wrapping_neg();
+            splits &= !bit;
+
+            rest.floating &= !bit;
+            pieces.push(Pattern {
+                fixed: (rest.fixed & !bit) | (!other.fixed & bit),
+                floating: rest.floating,
+            });
+            rest.fixed = (rest.fixed & !bit) | (other.fixed & bit);
+        }
+
+        pieces
+    }
+}
+
+/// Memory for part 2, stored as the writes themselves rather than the addresses they
+/// expand to. The stored patterns never overlap: a new write removes its addresses from the
+/// earlier ones, so the memory's sum is just each pattern's size times its value.
+#[derive(Default)]
+pub struct FloatingMemory {
+    entries: Vec<(Pattern, usize)>,
+}
+
+impl FloatingMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, pattern: Pattern, value: usize) {
+        let mut entries = Vec::with_capacity(self.entries.len() + 1);
+        for (existing, v) in self.entries.drain(..) {
+            for piece in existing.subtract(&pattern) {
+                entries.push((piece, v));
+            }
+        }
+        entries.push((pattern, value));
+        self.entries = entries;
+    }
+
+    /// The number of disjoint patterns currently stored.
+    pub fn entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn sum(&self) -> u128 {
+        self.entries
+            .iter()
+            .map(|(pattern, value)| pattern.size() * *value as u128)
+            .sum()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,4 +305,50 @@ mem[26] = 1",
         assert_eq!(208, memory.iter().fold(0, |acc, (_, v)| acc + v));
         Ok(())
     }
+
+    #[test]
+    fn test_part2_symbolic() -> Result<(), Error> {
+        let program = Program::parse(
+            "mask = 000000000000000000000000000000X1001X
+mem[42] = 100
+mask = 00000000000000000000000000000000X0XX
+mem[26] = 1",
+        )?;
+
+        let memory = program.run_part2_symbolic();
+        assert_eq!(208, memory.sum());
+        Ok(())
+    }
+
+    #[test]
+    fn symbolic_matches_naive() -> Result<(), Error> {
+        let program = Program::parse(
+            "mask = 0000000000000000000000000000X1X0X1XX
+mem[3] = 7
+mem[200] = 11
+mask = 000000000000000000000000000001XX0XX0
+mem[5] = 13
+mask = 000000000000000000000000000000000X1X
+mem[21] = 17
+mem[1] = 19",
+        )?;
+
+        let mut memory = HashMap::new();
+        program.run_part2(&mut memory);
+        let naive: usize = memory.values().sum();
+
+        assert_eq!(naive as u128, program.run_part2_symbolic().sum());
+        Ok(())
+    }
+
+    #[test]
+    fn many_floating_bits() {
+        let mut memory = FloatingMemory::new();
+        let everything = Pattern::from_mask(&"X".repeat(36), 0);
+        memory.write(everything, 3);
+        memory.write(Pattern::from_mask(&"0".repeat(36), 5), 10);
+
+        assert_eq!(3 * ((1u128 << 36) - 1) + 10, memory.sum());
+        assert_eq!(37, memory.entries());
+    }
 }
@@ -1,45 +1,82 @@
-use std::collections::HashMap;
-use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fs;
 
-fn main() {
-    let mut g = Game::new(&[0,6,1,7,2,19,20]);
-    if let Some(x) = g.nth(30000000-1) {
-        println!("The 30000000th number is: {}", x);
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    let starting = parse_starting(&fs::read_to_string(&args[1])?)?;
+    let turns: usize = match args.get(2) {
+        Some(n) => n.parse().expect("the number of turns should be a number"),
+        None => 30000000,
+    };
+
+    // Only the final turn is wanted here, so there's no need to keep the whole game.
+    let mut game = Game::new(&starting);
+    if let Some(x) = turns.checked_sub(1).and_then(|n| game.nth(n)) {
+        println!("The {}th number is: {}", turns, x);
+        println!("{} distinct numbers were spoken", game.distinct());
     } else {
         println!("The sequence ended");
     }
+
+    Ok(())
+}
+
+/// Reads comma separated starting numbers, which have to fit in a `u32` like everything else
+/// the game keeps.
+pub fn parse_starting(input: &str) -> Result<Vec<usize>, std::num::ParseIntError> {
+    input
+        .trim()
+        .split(',')
+        .map(|x| x.trim().parse::<u32>().map(|x| x as usize))
+        .collect()
 }
 
+/// The Van Eck style sequence from the puzzle. Rather than a map, the turn each number was
+/// last spoken is kept in a flat array indexed by the number, since no number can be larger
+/// than the number of turns played so far.
 pub struct Game {
-    last_num: usize,
-    last_index: usize,
-    starts: VecDeque<usize>,
-    prevs: HashMap<usize, (usize, Option<usize>)>,  // a map from number, to the last two indices it was referenced at
+    starts: Vec<u32>,
+    turn: u32,
+    last: u32,
+    // The turn each number was last spoken on, not counting the most recent turn. Zero means
+    // it hasn't been spoken.
+    last_seen: Vec<u32>,
+    distinct: usize,
 }
 
 impl Game {
+    /// Panics if a starting number doesn't fit in a `u32`; `parse_starting` never gives one.
     pub fn new(starting: &[usize]) -> Self {
-        let mut g = Game {
-            last_num: 0,
-            last_index: 0,
-            starts: starting.iter().map(|x| *x).collect(),
-            prevs: HashMap::new(),
-        };
-
-        starting.iter().for_each(|x| g.insert(*x));
-
-        g
+        let starts: Vec<u32> = starting
+            .iter()
+            .map(|&x| u32::try_from(x).expect("starting numbers should fit in a u32"))
+            .collect();
+        let largest = starting.iter().copied().max().unwrap_or(0);
+        Game {
+            starts,
+            turn: 0,
+            last: 0,
+            last_seen: vec![0; largest + 1],
+            distinct: 0,
+        }
     }
 
-    fn insert(&mut self, num: usize) {
-        self.last_index += 1;
+    /// How many turns have been played.
+    pub fn turn(&self) -> usize {
+        self.turn as usize
+    }
 
-        let res = match self.prevs.get(&num) {
-            Some((p1, _)) => (self.last_index, Some(*p1)),
-            None => (self.last_index, None),
-        };
+    /// How many different numbers have been spoken so far.
+    pub fn distinct(&self) -> usize {
+        self.distinct
+    }
 
-        self.prevs.insert(num, res);
+    fn seen(&mut self, num: u32) -> &mut u32 {
+        let index = num as usize;
+        if index >= self.last_seen.len() {
+            self.last_seen.resize((index + 1).max(self.last_seen.len() * 2), 0);
+        }
+        &mut self.last_seen[index]
     }
 }
 
@@ -47,22 +84,93 @@ impl Iterator for Game {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(x) = self.starts.pop_front() {
-            self.last_num = x;
-            return Some(x);
-        }
+        self.turn += 1;
+        let turn = self.turn;
 
-        let res = match self.prevs.get(&self.last_num) {
-            Some((p1, x)) => match x {
-                Some(p2) => p1 - p2,
-                None => 0,
-            },
-            None => 0,
+        let num = if let Some(&x) = self.starts.get(turn as usize - 1) {
+            x
+        } else {
+            match *self.seen(self.last) {
+                0 => 0,
+                prev => turn - 1 - prev,
+            }
         };
 
-        self.insert(res);
-        self.last_num = res;
-        Some(self.last_num)
+        if turn > 1 {
+            *self.seen(self.last) = turn - 1;
+        }
+        if *self.seen(num) == 0 {
+            self.distinct += 1;
+        }
+
+        self.last = num;
+        Some(num as usize)
+    }
+}
+
+/// A game played once for a fixed number of turns, keeping what's needed to answer questions
+/// about any of those turns without playing it again.
+pub struct Analysis {
+    spoken: Vec<u32>,
+    // The turns on which something was spoken for the first time, in order.
+    new_turns: Vec<u32>,
+    // Each number spoken and the turn it was first spoken on, sorted by number.
+    first_seen: Vec<(u32, u32)>,
+}
+
+impl Analysis {
+    pub fn run(starting: &[usize], turns: usize) -> Self {
+        let mut game = Game::new(starting);
+        let mut spoken = Vec::with_capacity(turns);
+        let mut new_turns = Vec::new();
+        while spoken.len() < turns {
+            let num = game.next().unwrap();
+            spoken.push(num as u32);
+            if game.distinct() > new_turns.len() {
+                new_turns.push(spoken.len() as u32);
+            }
+        }
+
+        let mut first_seen: Vec<_> = new_turns
+            .iter()
+            .map(|&turn| (spoken[turn as usize - 1], turn))
+            .collect();
+        first_seen.sort_unstable();
+
+        Analysis {
+            spoken,
+            new_turns,
+            first_seen,
+        }
+    }
+
+    /// The number spoken on the final turn.
+    pub fn last(&self) -> Option<usize> {
+        self.spoken.last().map(|&x| x as usize)
+    }
+
+    /// The number spoken on (1-based) turn `n`, if the game got that far.
+    pub fn value_at(&self, n: usize) -> Option<usize> {
+        self.spoken.get(n.checked_sub(1)?).map(|&x| x as usize)
+    }
+
+    /// The turn on which `value` was first spoken, if it was spoken at all.
+    pub fn first_occurrence(&self, value: usize) -> Option<usize> {
+        let i = self
+            .first_seen
+            .binary_search_by_key(&value, |&(v, _)| v as usize)
+            .ok()?;
+        Some(self.first_seen[i].1 as usize)
+    }
+
+    /// How many different numbers were spoken in the whole game.
+    pub fn distinct(&self) -> usize {
+        self.new_turns.len()
+    }
+
+    /// How many different numbers had been spoken by the end of (1-based) turn `n`.
+    pub fn distinct_at(&self, n: usize) -> usize {
+        self.new_turns.partition_point(|&turn| turn as usize <= n)
     }
 }
 
@@ -131,6 +239,37 @@ mod tests_part1 {
         // nth is 0 based, so use 2019 instead of 2020.
         assert_eq!(Some(1836), g.nth(2019));
     }
+
+    #[test]
+    fn queries() {
+        let analysis = Analysis::run(&[0, 3, 6], 2020);
+        assert_eq!(Some(436), analysis.value_at(2020));
+        assert_eq!(Some(0), analysis.value_at(1));
+        assert_eq!(None, analysis.value_at(0));
+        assert_eq!(None, analysis.value_at(2021));
+
+        // 0, 3, 6, 0, 3, 3, 1, 0, 4, 0
+        let analysis = Analysis::run(&[0, 3, 6], 10);
+        assert_eq!(Some(0), analysis.last());
+        assert_eq!(Some(4), analysis.value_at(9));
+        assert_eq!(Some(1), analysis.first_occurrence(0));
+        assert_eq!(Some(7), analysis.first_occurrence(1));
+        assert_eq!(Some(9), analysis.first_occurrence(4));
+        assert_eq!(None, analysis.first_occurrence(2));
+        assert_eq!(None, analysis.first_occurrence(100));
+        assert_eq!(5, analysis.distinct());
+        assert_eq!(0, analysis.distinct_at(0));
+        assert_eq!(3, analysis.distinct_at(6));
+        assert_eq!(4, analysis.distinct_at(8));
+        assert_eq!(5, analysis.distinct_at(100));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Ok(vec![0, 6, 1, 7, 2, 19, 20]), parse_starting("0,6,1,7,2,19,20\n"));
+        assert!(parse_starting("1,4294967296").is_err());
+        assert!(parse_starting("1,x").is_err());
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,279 @@
+//! Solves "put each name in exactly one slot" puzzles, where every name has a set of slots it
+//! is allowed to go in and no two names can share a slot. Names and slots are plain indices;
+//! callers keep their own tables to map them back to whatever they represent.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// No assignment exists. The `names` between them can only go in the `slots`, and there
+    /// are fewer slots than names, which proves it.
+    Unsolvable { names: Vec<usize>, slots: Vec<usize> },
+    /// More than one assignment exists. Two of them are given.
+    Ambiguous { first: Vec<usize>, second: Vec<usize> },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Unsolvable { names, slots } => write!(
+                f,
+                "no assignment: names {:?} can only go in slots {:?}",
+                names, slots
+            ),
+            Error::Ambiguous { first, second } => write!(
+                f,
+                "more than one assignment, e.g. {:?} and {:?}",
+                first, second
+            ),
+        }
+    }
+}
+
+/// Which slots each name may go in.
+#[derive(Clone, Debug)]
+pub struct Candidates {
+    slots: usize,
+    allowed: Vec<Vec<bool>>,
+}
+
+impl Candidates {
+    /// A matrix of `names` by `slots` with nothing allowed yet.
+    pub fn new(names: usize, slots: usize) -> Self {
+        Candidates {
+            slots,
+            allowed: vec![vec![false; slots]; names],
+        }
+    }
+
+    pub fn names(&self) -> usize {
+        self.allowed.len()
+    }
+
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    pub fn allow(&mut self, name: usize, slot: usize) {
+        self.allowed[name][slot] = true;
+    }
+
+    pub fn forbid(&mut self, name: usize, slot: usize) {
+        self.allowed[name][slot] = false;
+    }
+
+    pub fn is_allowed(&self, name: usize, slot: usize) -> bool {
+        self.allowed[name][slot]
+    }
+
+    /// The slots `name` may still go in, in ascending order.
+    pub fn options(&self, name: usize) -> impl Iterator<Item = usize> + '_ {
+        self.allowed[name]
+            .iter()
+            .enumerate()
+            .filter(|(_, &allowed)| allowed)
+            .map(|(slot, _)| slot)
+    }
+
+    /// Repeatedly takes every name with a single option and forbids that slot to all the other
+    /// names. Returns false if that leaves some name with nowhere to go, or two names fixed to
+    /// the same slot.
+    pub fn propagate(&mut self) -> bool {
+        let mut fixed = vec![false; self.names()];
+        loop {
+            let mut changed = false;
+            for name in 0..self.names() {
+                let options: Vec<usize> = self.options(name).take(2).collect();
+                let slot = match (options.first(), options.get(1)) {
+                    (None, _) => return false,
+                    (Some(&slot), None) => slot,
+                    _ => continue,
+                };
+                if fixed[name] {
+                    continue;
+                }
+
+                fixed[name] = true;
+                changed = true;
+                for (other, allowed) in self.allowed.iter_mut().enumerate() {
+                    if other != name && allowed[slot] {
+                        if fixed[other] {
+                            return false;
+                        }
+                        allowed[slot] = false;
+                    }
+                }
+            }
+
+            if !changed {
+                return true;
+            }
+        }
+    }
+
+    /// A maximum matching found with Hopcroft–Karp, as the slot given to each name. Names left
+    /// out of the matching get `None`.
+    pub fn maximum_matching(&self) -> Vec<Option<usize>> {
+        let mut slot_of: Vec<Option<usize>> = vec![None; self.names()];
+        let mut name_of: Vec<Option<usize>> = vec![None; self.slots];
+
+        loop {
+            // Layer the free names and everything reachable from them along alternating paths.
+            let mut layer = vec![usize::MAX; self.names()];
+            let mut queue = VecDeque::new();
+            for name in 0..self.names() {
+                if slot_of[name].is_none() {
+                    layer[name] = 0;
+                    queue.push_back(name);
+                }
+            }
+
+            let mut found = false;
+            while let Some(name) = queue.pop_front() {
+                for slot in self.options(name) {
+                    match name_of[slot] {
+                        None => found = true,
+                        Some(next) if layer[next] == usize::MAX => {
+                            layer[next] = layer[name] + 1;
+                            queue.push_back(next);
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+
+            if !found {
+                return slot_of;
+            }
+
+            for name in 0..self.names() {
+                if slot_of[name].is_none() {
+                    self.augment(name, &mut layer, &mut slot_of, &mut name_of);
+                }
+            }
+        }
+    }
+
+    fn augment(
+        &self,
+        name: usize,
+        layer: &mut [usize],
+        slot_of: &mut [Option<usize>],
+        name_of: &mut [Option<usize>],
+    ) -> bool {
+        for slot in self.options(name) {
+            let reached = match name_of[slot] {
+                None => true,
+                Some(next) => {
+                    layer[next] == layer[name] + 1 && self.augment(next, layer, slot_of, name_of)
+                }
+            };
+
+            if reached {
+                slot_of[name] = Some(slot);
+                name_of[slot] = Some(name);
+                return true;
+            }
+        }
+
+        // Nothing further along this name leads anywhere, so don't try it again this phase.
+        layer[name] = usize::MAX;
+        false
+    }
+
+    /// Checks that every name can be given its own slot. If not, finds a group of names with
+    /// fewer slots between them than there are names in the group.
+    pub fn check(&self) -> Result<Vec<usize>, Error> {
+        let matching = self.maximum_matching();
+        let unmatched = match matching.iter().position(Option::is_none) {
+            Some(name) => name,
+            None => return Ok(matching.into_iter().map(Option::unwrap).collect()),
+        };
+
+        let mut name_of = vec![None; self.slots];
+        for (name, slot) in matching.iter().enumerate() {
+            if let Some(slot) = slot {
+                name_of[*slot] = Some(name);
+            }
+        }
+
+        // Everything reachable from the unmatched name along alternating paths. Every slot
+        // reached is already taken, or the matching wouldn't be maximum, so the names found
+        // outnumber the slots by one.
+        let mut names = vec![unmatched];
+        let mut slots = Vec::new();
+        let mut seen = vec![false; self.slots];
+        let mut i = 0;
+        while i < names.len() {
+            for slot in self.options(names[i]) {
+                if !seen[slot] {
+                    seen[slot] = true;
+                    slots.push(slot);
+                    names.extend(name_of[slot]);
+                }
+            }
+            i += 1;
+        }
+
+        names.sort_unstable();
+        slots.sort_unstable();
+        Err(Error::Unsolvable { names, slots })
+    }
+
+    /// Every assignment, as the slot given to each name, in lexicographic order.
+    pub fn solutions(&self) -> Result<Vec<Vec<usize>>, Error> {
+        self.check()?;
+
+        let mut solutions = Vec::new();
+        self.clone().search(usize::MAX, &mut solutions);
+        solutions.sort();
+        Ok(solutions)
+    }
+
+    /// The only assignment, or an error saying whether there are none or several.
+    pub fn unique(&self) -> Result<Vec<usize>, Error> {
+        self.check()?;
+
+        let mut solutions = Vec::new();
+        self.clone().search(2, &mut solutions);
+        let mut solutions = solutions.into_iter();
+        match (solutions.next(), solutions.next()) {
+            (Some(only), None) => Ok(only),
+            (Some(first), Some(second)) => Err(Error::Ambiguous { first, second }),
+            (None, _) => unreachable!("check found an assignment"),
+        }
+    }
+
+    /// Propagates, then branches on the name with the fewest options left, skipping any
+    /// branch where a matching no longer covers every name.
+    fn search(mut self, limit: usize, solutions: &mut Vec<Vec<usize>>) {
+        if solutions.len() >= limit || !self.propagate() || self.check().is_err() {
+            return;
+        }
+
+        let branch = (0..self.names())
+            .map(|name| (self.options(name).count(), name))
+            .filter(|&(count, _)| count > 1)
+            .min();
+
+        match branch {
+            None => solutions.push(
+                (0..self.names())
+                    .map(|name| self.options(name).next().unwrap())
+                    .collect(),
+            ),
+            Some((_, name)) => {
+                for slot in self.options(name).collect::<Vec<_>>() {
+                    let mut next = self.clone();
+                    for other in 0..self.slots {
+                        if other != slot {
+                            next.forbid(name, other);
+                        }
+                    }
+                    next.search(limit, solutions);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use assignment::*;
+
+fn candidates(slots: usize, allowed: &[&[usize]]) -> Candidates {
+    let mut candidates = Candidates::new(allowed.len(), slots);
+    for (name, options) in allowed.iter().enumerate() {
+        for &slot in *options {
+            candidates.allow(name, slot);
+        }
+    }
+    candidates
+}
+
+#[test]
+fn propagate_test() {
+    let mut c = candidates(3, &[&[0, 1, 2], &[0], &[0, 1]]);
+    assert!(c.propagate());
+    assert_eq!(vec![2], c.options(0).collect::<Vec<_>>());
+    assert_eq!(vec![1], c.options(2).collect::<Vec<_>>());
+
+    let mut c = candidates(2, &[&[0], &[0], &[0, 1]]);
+    assert!(!c.propagate());
+}
+
+#[test]
+fn maximum_matching_test() {
+    let c = candidates(4, &[&[0, 1], &[0], &[1, 2, 3], &[2]]);
+    assert_eq!(vec![Some(1), Some(0), Some(3), Some(2)], c.maximum_matching());
+
+    let c = candidates(2, &[&[0], &[0], &[1]]);
+    assert_eq!(2, c.maximum_matching().iter().flatten().count());
+}
+
+#[test]
+fn unsolvable_test() {
+    let c = candidates(4, &[&[0, 1], &[1], &[0, 1], &[2, 3]]);
+    assert_eq!(
+        Err(Error::Unsolvable {
+            names: vec![0, 1, 2],
+            slots: vec![0, 1],
+        }),
+        c.unique()
+    );
+}
+
+#[test]
+fn solutions_test() {
+    // No name has a single option to start from.
+    let c = candidates(3, &[&[0, 1], &[1, 2], &[0, 2]]);
+    assert_eq!(
+        Ok(vec![vec![0, 1, 2], vec![1, 2, 0]]),
+        c.solutions()
+    );
+    assert_eq!(
+        Err(Error::Ambiguous {
+            first: vec![0, 1, 2],
+            second: vec![1, 2, 0],
+        }),
+        c.unique()
+    );
+
+    // More slots than names, and propagation alone gets stuck.
+    let c = candidates(5, &[&[0, 1, 4], &[0, 1], &[1, 2], &[2, 3]]);
+    assert_eq!(6, c.solutions().unwrap().len());
+    let c = candidates(4, &[&[0, 1], &[0, 1], &[1, 2], &[2, 3]]);
+    assert_eq!(Ok(vec![vec![0, 1, 2, 3], vec![1, 0, 2, 3]]), c.solutions());
+}
+
+#[test]
+fn unique_test() {
+    let c = candidates(3, &[&[0, 1, 2], &[0, 1], &[0]]);
+    assert_eq!(Ok(vec![2, 1, 0]), c.unique());
+}
@@ -1,3 +1,4 @@
+use assignment::Candidates;
 use std::collections::HashMap;
 
 fn main() -> Result<(), std::io::Error> {
@@ -7,15 +8,19 @@ fn main() -> Result<(), std::io::Error> {
     let conditions = ConditionSet::parse(&input);
     let tickets = Ticket::parse_nearby(&input);
     if let Some(mine) = Ticket::parse_yours(&input) {
-        let res = conditions.order_fields(&tickets);
-        let mut product = 1;
-        for (name, _) in &conditions.fields {
-            if name.starts_with("departure") {
-                product *= mine.field_values[res[&name]];
+        match conditions.order_fields(&tickets) {
+            Ok(res) => {
+                let mut product = 1;
+                for name in conditions.fields.keys() {
+                    if name.starts_with("departure") {
+                        product *= mine.field_values[res[&name]];
+                    }
+                }
+
+                println!("The product is: {}", product);
             }
+            Err(e) => println!("Couldn't order the fields: {}", e),
         }
-
-        println!("The product is: {}", product);
     } else {
         println!("Couldn't parse my ticket")
     }
@@ -50,7 +55,7 @@ impl ConditionSet {
     pub fn parse(input: &str) -> Self {
         let mut fields = HashMap::new();
         for line in input.lines() {
-            if line == "" { 
+            if line.is_empty() {
                 break;
             }
             let (field, ranges) = split_once(line, ": ");
@@ -63,7 +68,7 @@ impl ConditionSet {
             );
         }
 
-        ConditionSet { fields: fields }
+        ConditionSet { fields }
     }
 
     pub fn invalid_field_values(&self, ticket: &Ticket) -> Vec<usize> {
@@ -72,7 +77,7 @@ impl ConditionSet {
         for v in &ticket.field_values {
             let vv = *v;
             let mut any_valid = false;
-            for (_, (r1, r2)) in &self.fields {
+            for (r1, r2) in self.fields.values() {
                 if vv >= r1.min && vv <= r1.max || vv >= r2.min && vv <= r2.max {
                     any_valid = true;
                     break;
@@ -90,7 +95,7 @@ impl ConditionSet {
     pub fn is_possibly_valid(&self, ticket: &Ticket) -> bool {
         for v in &ticket.field_values {
             let mut any_valid = false;
-            for (_, (r1, r2)) in &self.fields {
+            for (r1, r2) in self.fields.values() {
                 if Range::in_ranges(*v, r1, r2) {
                     any_valid = true;
                     break;
@@ -105,45 +110,30 @@ impl ConditionSet {
         true
     }
 
-    pub fn order_fields<'a>(&self, tickets: &[Ticket]) -> HashMap<&String, usize> {
+    /// Works out which position each field is in. A field can go in any position where every
+    /// valid ticket's value satisfies its ranges, and it's an error unless exactly one way of
+    /// placing all the fields remains.
+    pub fn order_fields(
+        &self,
+        tickets: &[Ticket],
+    ) -> Result<HashMap<&String, usize>, assignment::Error> {
         let valid_tickets: Vec<_> = tickets.iter().filter(|t| self.is_possibly_valid(t)).collect();
 
-        let mut valid_positions_by_name = HashMap::new();
-        for (name, (r1, r2)) in self.fields.iter() {
+        let mut names: Vec<_> = self.fields.keys().collect();
+        names.sort();
+
+        let mut candidates = Candidates::new(names.len(), self.fields.len());
+        for (n, name) in names.iter().enumerate() {
+            let (r1, r2) = &self.fields[*name];
             for i in 0..self.fields.len() {
                 if valid_tickets.iter().all(|t| Range::in_ranges(t.field_values[i], r1, r2)) {
-                    valid_positions_by_name.entry(name).or_insert(Vec::new()).push(i);
-                }
-            }
-        }
-
-        let mut res = HashMap::new();
-        while !valid_positions_by_name.is_empty() {
-            valid_positions_by_name = Self::fix_single_position(&mut res, & valid_positions_by_name);
-        }
-
-       res
-    }
-
-    fn fix_single_position<'a>(res: &mut HashMap<&'a String, usize>, valid_positions_by_name: &HashMap<&'a String, Vec<usize>>) ->  HashMap<&'a String, Vec<usize>>{
-        let mut x = HashMap::new();
-
-        for (name, positions) in valid_positions_by_name {
-            if positions.len() == 1 {
-                res.insert(name, positions[0]);
-
-                for (n, p2) in valid_positions_by_name {
-                    if n != name {
-                        let mut p = p2.clone();
-                        vec_remove_item(&mut p, &positions[0]);
-                        x.insert(*n, p);
-                    }
+                    candidates.allow(n, i);
                 }
-                break;
             }
         }
 
-        x
+        let positions = candidates.unique()?;
+        Ok(names.into_iter().zip(positions).collect())
     }
 }
 
@@ -188,18 +178,7 @@ impl Ticket {
 }
 
 fn split_once<'a>(in_string: &'a str, split_on: &str) -> (&'a str, &'a str) {
-    let mut splitter = in_string.splitn(2, split_on);
-    let first = splitter.next().unwrap();
-    let second = splitter.next().unwrap();
-    (first, second)
-}
-
-fn vec_remove_item<T: PartialEq>(vec: &mut Vec<T>, item: &T) -> Option<T> {
-    let pos = match vec.iter().position(|x| *x == *item) {
-        Some(x) => x,
-        None => return None,
-    };
-    Some(vec.remove(pos))
+    in_string.split_once(split_on).unwrap()
 }
 
 #[cfg(test)]
@@ -249,9 +228,30 @@ nearby tickets:
         let conditions = ConditionSet::parse(input);
         let tickets = Ticket::parse_nearby(input);
 
-        let res = conditions.order_fields(&tickets);
+        let res = conditions.order_fields(&tickets).unwrap();
         assert_eq!(0, res[&String::from("row")]);
         assert_eq!(1, res[&String::from("class")]);
         assert_eq!(2, res[&String::from("seat")]);
     }
+
+    #[test]
+    fn ambiguous() {
+        let input = "class: 0-1 or 4-19
+row: 0-5 or 8-19
+
+your ticket:
+11,12
+
+nearby tickets:
+5,9
+15,4";
+
+        let conditions = ConditionSet::parse(input);
+        let tickets = Ticket::parse_nearby(input);
+
+        assert!(matches!(
+            conditions.order_fields(&tickets),
+            Err(assignment::Error::Ambiguous { .. })
+        ));
+    }
 }
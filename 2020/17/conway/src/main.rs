@@ -1,357 +1,198 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 
-fn main() {
-    let mut state = Dimension::parse(
-"#.#####.
-#..##...
-.##..#..
-#.##.###
-.#.#.#..
-#.##..#.
-#####..#
-..#.#.##",
-    );
-
-    for i in 0..6 {
+fn main() -> Result<(), std::io::Error> {
+    let args: Vec<String> = std::env::args().collect();
+    let input = std::fs::read_to_string(&args[1])?;
+    let dimensions = match args.get(2) {
+        Some(d) => d.parse().expect("the dimension count should be a number"),
+        None => 4,
+    };
+    let cycles = match args.get(3) {
+        Some(c) => c.parse().expect("the cycle count should be a number"),
+        None => 6,
+    };
+
+    let count = match dimensions {
+        2 => boot::<2>(&input, cycles),
+        3 => boot::<3>(&input, cycles),
+        4 => boot::<4>(&input, cycles),
+        5 => boot::<5>(&input, cycles),
+        6 => boot::<6>(&input, cycles),
+        7 => boot::<7>(&input, cycles),
+        8 => boot::<8>(&input, cycles),
+        _ => {
+            println!("Only 2 to 8 dimensions are supported");
+            return Ok(());
+        }
+    };
+
+    println!("After boot, there are {} active cubes", count);
+    Ok(())
+}
+
+fn boot<const D: usize>(input: &str, cycles: usize) -> usize {
+    let mut state = ConwayN::<D>::parse(input);
+    for _ in 0..cycles {
         state = state.cycle();
-        println!("------- After step {} -----------", i);
-        println!("{}", state);
     }
-
-    println!("After boot, there are {} cycles", state.active_count());
+    state.active_count()
 }
 
-pub struct Plane {
-    y_points: HashMap<isize, HashSet<isize>>,
-    minx: isize,
-    miny: isize,
-    maxx: isize,
-    maxy: isize,
+/// A pocket dimension with `D` dimensions, started from a single 2D slice. Only the first two
+/// coordinates come from the starting grid, so every other coordinate starts at zero and the
+/// state stays symmetric under negating any of them or swapping any two of them. Because of
+/// that only the points whose extra coordinates are non-negative and in ascending order are
+/// stored, and each one stands in for all of its mirror images.
+pub struct ConwayN<const D: usize> {
+    active: HashSet<[isize; D]>,
 }
 
-impl Plane {
+impl<const D: usize> ConwayN<D> {
     pub fn parse(input: &str) -> Self {
-        let mut map = HashMap::new();
+        assert!(D >= 2, "the starting grid needs at least two dimensions");
+
+        let mut active = HashSet::new();
         for (y, line) in input.lines().enumerate() {
-            let mut hashset = HashSet::new();
             for (x, c) in line.chars().enumerate() {
                 if c == '#' {
-                    hashset.insert(x as isize);
+                    let mut point = [0; D];
+                    point[0] = x as isize;
+                    point[1] = y as isize;
+                    active.insert(point);
                 }
             }
-            map.insert(y as isize, hashset);
-        }
-        let (minx, miny, maxx, maxy) = Self::get_mins_maxes(&map);
-
-        Self {
-            y_points: map,
-            minx: minx,
-            miny: miny,
-            maxx: maxx,
-            maxy: maxy,
-        }
-    }
-
-    pub fn create_from(map: HashMap<isize, HashSet<isize>>) -> Self {
-        let (minx, miny, maxx, maxy) = Self::get_mins_maxes(&map);
-        Self {
-            y_points: map,
-            minx: minx,
-            miny: miny,
-            maxx: maxx,
-            maxy: maxy,
-        }
-    }
-
-    fn get_mins_maxes(map: &HashMap<isize, HashSet<isize>>) -> (isize, isize, isize, isize) {
-        let mut minx = isize::MAX;
-        let mut miny = isize::MAX;
-        let mut maxx = isize::MIN;
-        let mut maxy = isize::MIN;
-        for (y, xs) in map {
-            if *y > maxy { maxy = *y; }
-            if *y < miny { miny = *y; }
-
-            for x in xs {
-                if *x > maxx { maxx = *x; }
-                if *x < minx { minx = *x; }
-            }
         }
 
-        (minx, miny, maxx, maxy)
+        ConwayN { active }
     }
 
+    /// The number of active cubes, counting every mirror image.
     pub fn active_count(&self) -> usize {
-        self.y_points.iter().map(|(_, xs)| xs.len()).sum()
+        self.active.iter().map(orbit_size).sum()
     }
 
-    pub fn is_active(&self, x: isize, y: isize) -> bool {
-        match self.y_points.get(&y) {
-            Some(xs) => match xs.get(&x) {
-                Some(_) => true,
-                None => false,
-            },
-            None => false,
-        }
+    pub fn is_active(&self, point: [isize; D]) -> bool {
+        self.active.contains(&canonical(point))
     }
-}
 
-impl std::fmt::Display for Plane {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        for y in self.miny..self.maxy+1 {
-            for x in self.minx..self.maxx+1 {
-                let res = match self.is_active(x, y) {
-                    true => "#",
-                    false => ".",
-                };
-                write!(f, "{}", res)?;
+    pub fn cycle(&self) -> Self {
+        let offsets = neighbour_offsets::<D>();
+
+        // Only stored points, or points next to one, can be active after the cycle.
+        let mut candidates = HashSet::new();
+        for point in &self.active {
+            candidates.insert(*point);
+            for offset in &offsets {
+                candidates.insert(canonical(add(*point, offset)));
             }
-            writeln!(f, "")?;
         }
 
-        Ok(())
-    }
-}
-
-pub struct Cube {
-    z_planes: HashMap<isize, Plane>,
-    minx: isize,
-    miny: isize,
-    minz: isize,
-    maxx: isize,
-    maxy: isize,
-    maxz: isize,
-}
+        let active = candidates
+            .into_iter()
+            .filter(|point| {
+                let adjacent = offsets
+                    .iter()
+                    .filter(|offset| self.is_active(add(*point, offset)))
+                    .count();
+                match self.active.contains(point) {
+                    true => adjacent == 2 || adjacent == 3,
+                    false => adjacent == 3,
+                }
+            })
+            .collect();
 
-impl Cube {
-    pub fn parse(input: &str) -> Self {
-        let mut zmap = HashMap::new();
-        let p = Plane::parse(input);
-        let minx = p.minx;
-        let miny = p.miny;
-        let maxx = p.maxx;
-        let maxy = p.maxy;
-        zmap.insert(0, p);
-        Cube {
-            z_planes: zmap,
-            minx: minx,
-            miny: miny,
-            minz: 0,
-            maxx: maxx,
-            maxy: maxy,
-            maxz: 0,
-        }
+        ConwayN { active }
     }
+}
 
-    pub fn create_from(zmap: HashMap<isize, Plane>) -> Self {
-        let minx = zmap.iter().map(|(_, p)| p.minx).min().unwrap();
-        let miny = zmap.iter().map(|(_, p)| p.miny).min().unwrap();
-        let minz = *zmap.iter().map(|(z, _)| z).min().unwrap();
-        let maxx = zmap.iter().map(|(_, p)| p.maxx).max().unwrap();
-        let maxy = zmap.iter().map(|(_, p)| p.maxy).max().unwrap();
-        let maxz = *zmap.iter().map(|(z, _)| z).max().unwrap();
-        Cube {
-            z_planes: zmap,
-            minx: minx,
-            miny: miny,
-            minz: minz,
-            maxx: maxx,
-            maxy: maxy,
-            maxz: maxz,
+impl<const D: usize> std::fmt::Display for ConwayN<D> {
+    /// Prints the x/y slice at each stored combination of the other coordinates.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        if self.active.is_empty() {
+            return Ok(());
         }
-    }
 
-    pub fn active_count(&self) -> usize {
-        self.z_planes.iter().map(|(_, p)| p.active_count()).sum()
-    }
+        let minx = self.active.iter().map(|p| p[0]).min().unwrap();
+        let maxx = self.active.iter().map(|p| p[0]).max().unwrap();
+        let miny = self.active.iter().map(|p| p[1]).min().unwrap();
+        let maxy = self.active.iter().map(|p| p[1]).max().unwrap();
 
-    pub fn cycle3d(&self) -> Self {
-        let mut zmap = HashMap::new();
-        for z in self.minz-1..self.maxz + 2 {
-            let mut ymap = HashMap::new();
-            for y in self.miny-1..self.maxy + 2 {
-                let mut xs = HashSet::new();
-                for x in self.minx..self.maxx + 2 {
-                    let adj = self.active_adjacent3d(x, y, z);
-                    let res = match self.is_active(x, y, z) {
-                        true => adj == 2 || adj == 3,
-                        false => adj == 3,
-                    };
-
-                    if res { 
-                        xs.insert(x);
-                    }
-                }
-                ymap.insert(y, xs);
-            }
-            zmap.insert(z, Plane::create_from(ymap));
-        }
-
-        Cube{
-            z_planes: zmap,
-            minx: self.minx-1,
-            miny: self.miny-1,
-            minz: self.minz-1,
-            maxx: self.maxx+1,
-            maxy: self.maxy+1,
-            maxz: self.maxz+1,
+        let mut slices: HashMap<Vec<isize>, HashSet<(isize, isize)>> = HashMap::new();
+        for p in &self.active {
+            slices.entry(p[2..].to_vec()).or_default().insert((p[0], p[1]));
         }
-    }
-    
-    fn active_adjacent3d(&self, x: isize, y: isize, z: isize) -> usize {
-        let mut count = 0;
-        for zz in z-1..z+2 {
-            for yy in y-1..y+2 {
-                for xx in x-1..x+2 {
-                    if self.is_active(xx, yy, zz) {
-                        count += 1;
-                    }
+        let mut keys: Vec<_> = slices.keys().collect();
+        keys.sort();
+
+        for key in keys {
+            writeln!(f, "{:?}", key)?;
+            for y in miny..maxy + 1 {
+                for x in minx..maxx + 1 {
+                    let res = match slices[key].contains(&(x, y)) {
+                        true => "#",
+                        false => ".",
+                    };
+                    write!(f, "{}", res)?;
                 }
+                writeln!(f)?;
             }
+            writeln!(f)?;
         }
 
-        if self.is_active(x, y, z) {
-            count -= 1;
-        }
-        count
-    }
-
-    fn is_active(&self, x: isize, y: isize, z: isize) -> bool {
-        match self.z_planes.get(&z) {
-            Some(p) => p.is_active(x, y),
-            None => false
-        }
-    }
-}
-
-impl std::fmt::Display for Cube {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        for z in self.minz..self.maxz+1 {
-            writeln!(f, "z={}", z)?;
-            writeln!(f, "{}", self.z_planes[&z])?;
-        }
         Ok(())
     }
 }
 
-struct Dimension {
-    cubes: HashMap<isize, Cube>,
-    minx: isize,
-    miny: isize,
-    minz: isize,
-    minw: isize,
-    maxx: isize,
-    maxy: isize,
-    maxz: isize,
-    maxw: isize,
+/// The mirror image of `point` that gets stored.
+fn canonical<const D: usize>(mut point: [isize; D]) -> [isize; D] {
+    for c in &mut point[2..] {
+        *c = c.abs();
+    }
+    point[2..].sort_unstable();
+    point
 }
 
-impl Dimension {
-    pub fn parse(input: &str) -> Self {
-        let mut cubes = HashMap::new();
-        let c = Cube::parse(input);
-        let minx = c.minx;
-        let miny = c.miny;
-        let minz = c.minz;
-        let maxx = c.maxx;
-        let maxy = c.maxy;
-        let maxz = c.maxz;
-
-        cubes.insert(0, c);
-        Self {
-            cubes: cubes,
-            minx: minx,
-            miny: miny,
-            minz: minz,
-            minw: 0,
-            maxx: maxx,
-            maxy: maxy,
-            maxz: maxz,
-            maxw: 0,
-        }
+/// How many distinct points a stored point stands in for: every way of ordering its extra
+/// coordinates, times both signs of each non-zero one.
+fn orbit_size<const D: usize>(point: &[isize; D]) -> usize {
+    let extra = &point[2..];
+    let mut size = factorial(extra.len());
+    let mut i = 0;
+    while i < extra.len() {
+        let run = extra[i..].iter().take_while(|&&c| c == extra[i]).count();
+        size /= factorial(run);
+        i += run;
     }
 
-    pub fn active_count(&self) -> usize {
-        self.cubes.iter().map(|(_, c)| c.active_count()).sum()
-    }
-
-    pub fn cycle(&self) -> Self {
-        let mut cubes = HashMap::new();
-        for w in self.minw-1..self.maxw + 2 {
-            let mut zmap = HashMap::new();
-            for z in self.minz-1..self.maxz + 2 {
-                let mut ymap = HashMap::new();
-                for y in self.miny-1..self.maxy + 2 {
-                    let mut xs = HashSet::new();
-                    for x in self.minx..self.maxx + 2 {
-                        let adj = self.active_adjacent(x, y, z, w);
-                        let res = match self.is_active(x, y, z, w) {
-                            true => adj == 2 || adj == 3,
-                            false => adj == 3,
-                        };
-
-                        if res { 
-                            xs.insert(x);
-                        }
-                    }
-                    ymap.insert(y, xs);
-                }
-                zmap.insert(z, Plane::create_from(ymap));
-            }
-            cubes.insert(w, Cube::create_from(zmap));
-        }
-
-        Self {
-            cubes: cubes,
-            minx: self.minx-1,
-            miny: self.miny-1,
-            minz: self.minz-1,
-            minw: self.minw-1,
-            maxx: self.maxx+1,
-            maxy: self.maxy+1,
-            maxz: self.maxz+1,
-            maxw: self.maxw+1,
-        }
-    }
+    size << extra.iter().filter(|&&c| c != 0).count()
+}
 
-    fn active_adjacent(&self, x: isize, y: isize, z: isize, w: isize) -> usize {
-        let mut count = 0;
-        for ww in w-1..w+2 {
-            for zz in z-1..z+2 {
-                for yy in y-1..y+2 {
-                    for xx in x-1..x+2 {
-                        if self.is_active(xx, yy, zz, ww) {
-                            count += 1;
-                        }
-                    }
-                }
-            }
-        }
+fn factorial(n: usize) -> usize {
+    (1..=n).product()
+}
 
-        if self.is_active(x, y, z, w) {
-            count -= 1;
+fn neighbour_offsets<const D: usize>() -> Vec<[isize; D]> {
+    let mut offsets = Vec::new();
+    for n in 0..3usize.pow(D as u32) {
+        let mut offset = [0; D];
+        let mut rest = n;
+        for c in &mut offset {
+            *c = (rest % 3) as isize - 1;
+            rest /= 3;
         }
-
-        count
-    }
-
-    fn is_active(&self, x: isize, y: isize, z: isize, w: isize) -> bool {
-        match self.cubes.get(&w) {
-            Some(c) => c.is_active(x, y, z),
-            None => false,
+        if offset != [0; D] {
+            offsets.push(offset);
         }
     }
+    offsets
 }
 
-impl std::fmt::Display for Dimension {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        for w in self.minw..self.maxw+1 {
-            writeln!(f, "w = {}, {}", w, self.cubes[&w])?;
-        }
-
-        Ok(())
+fn add<const D: usize>(mut point: [isize; D], offset: &[isize; D]) -> [isize; D] {
+    for (c, o) in point.iter_mut().zip(offset) {
+        *c += o;
     }
+    point
 }
 
 #[cfg(test)]
@@ -360,20 +201,35 @@ mod tests_part1 {
 
     #[test]
     fn test() {
-        let mut state = Cube::parse(
+        let mut state = ConwayN::<3>::parse(
 ".#.
 ..#
 ###",
         );
 
         for i in 0..6 {
-            state = state.cycle3d();
+            state = state.cycle();
             println!("------- After step {} -----------", i);
             println!("{}", state);
         }
 
         assert_eq!(112, state.active_count())
     }
+
+    #[test]
+    fn plane() {
+        // A blinker flips between vertical and horizontal.
+        let mut state = ConwayN::<2>::parse(
+".#.
+.#.
+.#.",
+        );
+
+        state = state.cycle();
+        assert_eq!(3, state.active_count());
+        assert!(state.is_active([0, 1]) && state.is_active([1, 1]) && state.is_active([2, 1]));
+        assert!(!state.is_active([1, 0]));
+    }
 }
 
 #[cfg(test)]
@@ -382,7 +238,7 @@ mod tests_part2 {
 
     #[test]
     fn test() {
-        let mut state = Dimension::parse(
+        let mut state = ConwayN::<4>::parse(
 ".#.
 ..#
 ###",
@@ -391,10 +247,66 @@ mod tests_part2 {
         for i in 0..6 {
             state = state.cycle();
             println!("------- After step {} -----------", i);
-            println!("Bounds: x:{}-{}, y:{}-{}, z:{}-{}, w:{}-{}", state.minx, state.maxx, state.miny, state.maxy, state.minz, state.maxz, state.minw, state.maxw);
             println!("{}", state);
         }
 
         assert_eq!(848, state.active_count())
     }
+
+    #[test]
+    fn symmetry() {
+        let mut state = ConwayN::<5>::parse(
+".#.
+..#
+###",
+        );
+        state = state.cycle().cycle();
+
+        let stored = *state.active.iter().find(|p| p[4] != 0).unwrap();
+        let [x, y, z, w, v] = stored;
+        assert!(state.is_active([x, y, -v, z, -w]));
+        assert!(!state.is_active([x, y, z, w, v + 20]));
+        assert_eq!(orbit_size(&[0, 0, 0, 1, 1]), 12);
+        assert_eq!(orbit_size(&[0, 0, 0, 0, 0]), 1);
+        assert_eq!(orbit_size(&[0, 0, 1, 2, 3]), 48);
+    }
+
+    #[test]
+    fn matches_unfolded() {
+        let start = ".#.
+..#
+###";
+
+        // Step every point directly, without any folding.
+        let mut active: HashSet<[isize; 5]> = HashSet::new();
+        for (y, line) in start.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c == '#' {
+                    active.insert([x as isize, y as isize, 0, 0, 0]);
+                }
+            }
+        }
+        let offsets = neighbour_offsets::<5>();
+        for _ in 0..3 {
+            let mut counts: HashMap<[isize; 5], usize> = HashMap::new();
+            for p in &active {
+                for o in &offsets {
+                    *counts.entry(add(*p, o)).or_default() += 1;
+                }
+            }
+            active = counts
+                .into_iter()
+                .filter(|(p, n)| *n == 3 || (*n == 2 && active.contains(p)))
+                .map(|(p, _)| p)
+                .collect();
+        }
+
+        let mut state = ConwayN::<5>::parse(start);
+        for _ in 0..3 {
+            state = state.cycle();
+        }
+
+        assert_eq!(active.len(), state.active_count());
+        assert!(active.iter().all(|p| state.is_active(*p)));
+    }
 }
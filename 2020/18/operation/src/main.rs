@@ -1,11 +1,28 @@
+use std::fmt;
 use std::io::{BufRead, BufReader};
 
 fn main() -> Result<(), Error> {
     let args: Vec<_> = std::env::args().collect();
     let reader = BufReader::new(std::fs::File::open(&args[1])?);
+    let precedence = match args.get(2).map(String::as_str) {
+        None | Some("addition") => Precedence::addition_first(),
+        Some("same") => Precedence::same(),
+        Some("standard") => Precedence::standard(),
+        Some(other) => {
+            println!("Unknown precedence '{}', expected same, addition or standard", other);
+            return Ok(());
+        }
+    };
+
     let mut sum = 0;
-    for line in reader.lines() {
-        sum += evaluate(&line?)?
+    for (index, line) in reader.lines().enumerate() {
+        match parse(&line?, &precedence).and_then(|e| e.evaluate()) {
+            Ok(value) => sum += value,
+            Err(e) => {
+                println!("Line {}: {}", index + 1, e);
+                return Ok(());
+            }
+        }
     }
 
     println!("The sum is {}", sum);
@@ -13,105 +30,333 @@ fn main() -> Result<(), Error> {
     Ok(())
 }
 
+/// Evaluates `input` with the puzzle's part 2 rules, where addition happens before
+/// multiplication.
 pub fn evaluate(input: &str) -> Result<isize, Error> {
-    let (res, _) = evaluate_helper(input)?;
-    Ok(res)
+    parse(input, &Precedence::addition_first())?.evaluate()
 }
 
-fn evaluate_helper(input: &str) -> Result<(isize, &str), Error> {
-    let (mut left, mut remaining) = parse_product(input)?;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
 
-    while !remaining.is_empty() {
-        let t = peek_token(remaining)?;
-        match t {
-            Token::Multiplication => {
-                let (right, r) = parse_product(&remaining[3..])?;
-                remaining = r;
-                left = left * right;
-            }
-            Token::CloseParen => {
-                return Ok((left, &remaining[1..]));
-            }
-            _ => {
-                return Err(Error::UnexpectedToken(t));
-            }
+impl Op {
+    pub fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Subtract => '-',
+            Op::Multiply => '*',
+            Op::Divide => '/',
         }
     }
 
-    Ok((left, remaining))
+    fn apply(self, left: isize, right: isize) -> Result<isize, Error> {
+        let result = match self {
+            Op::Add => left.checked_add(right),
+            Op::Subtract => left.checked_sub(right),
+            Op::Multiply => left.checked_mul(right),
+            Op::Divide if right == 0 => return Err(Error::DivisionByZero),
+            Op::Divide => left.checked_div(right),
+        };
+        result.ok_or(Error::Overflow)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Number(isize),
+    Op(Op),
+    OpenParen,
+    CloseParen,
 }
 
-fn parse_product(input: &str) -> Result<(isize, &str), Error> {
-    let (mut left, mut remaining) = parse_term(input)?;
-    loop {
-        let t = peek_token(remaining)?;
-        match t {
-            Token::Addition => {
-                let (right, rem) = parse_term(&remaining[3..])?;
-                remaining = rem;
-                left += right;
+/// A token, with `column` being the 1-based column where it starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub column: usize,
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let column = i + 1;
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '+' => TokenKind::Op(Op::Add),
+            '-' => TokenKind::Op(Op::Subtract),
+            '*' => TokenKind::Op(Op::Multiply),
+            '/' => TokenKind::Op(Op::Divide),
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            '0'..='9' => {
+                let mut end = i + 1;
+                while let Some(&(j, d)) = chars.peek() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    end = j + 1;
+                    chars.next();
+                }
+                match input[i..end].parse() {
+                    Ok(n) => TokenKind::Number(n),
+                    Err(_) => return Err(Error::NumberTooLarge { column }),
+                }
             }
-            Token::Multiplication => return Ok((left, remaining)),
-            Token::CloseParen => return Ok((left, remaining)),
-            Token::EOF => return Ok((left, remaining)),
-            _ => return Err(Error::UnexpectedToken(t)),
+            found => return Err(Error::UnexpectedCharacter { column, found }),
+        };
+        tokens.push(Token { kind, column });
+    }
+
+    Ok(tokens)
+}
+
+/// How tightly each operator binds. Higher levels bind tighter, and operators on the same
+/// level are evaluated left to right.
+#[derive(Clone, Debug)]
+pub struct Precedence {
+    add: u8,
+    subtract: u8,
+    multiply: u8,
+    divide: u8,
+}
+
+impl Precedence {
+    /// Every operator on the same level, as in part 1 of the puzzle.
+    pub fn same() -> Self {
+        Precedence {
+            add: 1,
+            subtract: 1,
+            multiply: 1,
+            divide: 1,
+        }
+    }
+
+    /// Addition and subtraction before multiplication and division, as in part 2.
+    pub fn addition_first() -> Self {
+        Precedence {
+            add: 2,
+            subtract: 2,
+            multiply: 1,
+            divide: 1,
+        }
+    }
+
+    /// Multiplication and division before addition and subtraction.
+    pub fn standard() -> Self {
+        Precedence {
+            add: 1,
+            subtract: 1,
+            multiply: 2,
+            divide: 2,
+        }
+    }
+
+    pub fn with(mut self, op: Op, level: u8) -> Self {
+        *self.level_mut(op) = level;
+        self
+    }
+
+    pub fn level(&self, op: Op) -> u8 {
+        match op {
+            Op::Add => self.add,
+            Op::Subtract => self.subtract,
+            Op::Multiply => self.multiply,
+            Op::Divide => self.divide,
         }
     }
+
+    fn level_mut(&mut self, op: Op) -> &mut u8 {
+        match op {
+            Op::Add => &mut self.add,
+            Op::Subtract => &mut self.subtract,
+            Op::Multiply => &mut self.multiply,
+            Op::Divide => &mut self.divide,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(isize),
+    Binary(Op, Box<Expr>, Box<Expr>),
 }
 
-fn parse_term(input: &str) -> Result<(isize, &str), Error> {
-    let t = peek_token(input)?;
-    match t {
-        Token::OpenParen => evaluate_helper(&input[1..]),
-        Token::Number(num) => Ok((num, &input[1..])),
-        _ => Err(Error::UnexpectedToken(t)),
+impl Expr {
+    pub fn evaluate(&self) -> Result<isize, Error> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Binary(op, left, right) => op.apply(left.evaluate()?, right.evaluate()?),
+        }
+    }
+
+    /// Writes the expression back out with only the parentheses that `precedence` needs to
+    /// read it the same way again.
+    pub fn pretty(&self, precedence: &Precedence) -> String {
+        let mut out = String::new();
+        self.write(precedence, &mut out);
+        out
+    }
+
+    fn write(&self, precedence: &Precedence, out: &mut String) {
+        match self {
+            Expr::Number(n) => out.push_str(&n.to_string()),
+            Expr::Binary(op, left, right) => {
+                let level = precedence.level(*op);
+                // Everything is left associative, so a right operand on the same level still
+                // needs parentheses.
+                write_operand(left, precedence, out, |l| l < level);
+                out.push(' ');
+                out.push(op.symbol());
+                out.push(' ');
+                write_operand(right, precedence, out, |l| l <= level);
+            }
+        }
     }
 }
 
-fn peek_token(input: &str) -> Result<Token, Error> {
-    for c in input.bytes() {
-        if c == b' ' {
-            continue;
+fn write_operand(
+    operand: &Expr,
+    precedence: &Precedence,
+    out: &mut String,
+    needs_parens: impl Fn(u8) -> bool,
+) {
+    match operand {
+        Expr::Binary(op, _, _) if needs_parens(precedence.level(*op)) => {
+            out.push('(');
+            operand.write(precedence, out);
+            out.push(')');
         }
+        _ => operand.write(precedence, out),
+    }
+}
 
-        return match c {
-            b'+' => Ok(Token::Addition),
-            b'*' => Ok(Token::Multiplication),
-            b'(' => Ok(Token::OpenParen),
-            b')' => Ok(Token::CloseParen),
-            b'0' => Ok(Token::Number(0)),
-            b'1' => Ok(Token::Number(1)),
-            b'2' => Ok(Token::Number(2)),
-            b'3' => Ok(Token::Number(3)),
-            b'4' => Ok(Token::Number(4)),
-            b'5' => Ok(Token::Number(5)),
-            b'6' => Ok(Token::Number(6)),
-            b'7' => Ok(Token::Number(7)),
-            b'8' => Ok(Token::Number(8)),
-            b'9' => Ok(Token::Number(9)),
-            _ => Err(Error::InvalidOperator {}),
-        };
+pub fn parse(input: &str, precedence: &Precedence) -> Result<Expr, Error> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        end: input.len() + 1,
+        precedence,
+    };
+
+    let expr = parser.expression(0)?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(Error::UnexpectedToken {
+            column: token.column,
+            expected: "an operator",
+        }),
     }
+}
 
-    Ok(Token::EOF)
+/// A Pratt parser: each loop around `expression` takes the next operator as long as it binds
+/// at least as tightly as `min_level`.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    end: usize,
+    precedence: &'a Precedence,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Token {
-    Addition,
-    Multiplication,
-    OpenParen,
-    CloseParen,
-    Number(isize),
-    EOF,
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<Token, Error> {
+        match self.peek() {
+            Some(token) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => Err(Error::UnexpectedEnd {
+                column: self.end,
+                expected,
+            }),
+        }
+    }
+
+    fn expression(&mut self, min_level: u16) -> Result<Expr, Error> {
+        let mut left = self.operand()?;
+        while let Some(Token {
+            kind: TokenKind::Op(op),
+            ..
+        }) = self.peek()
+        {
+            let level = self.precedence.level(op) as u16;
+            if level < min_level {
+                break;
+            }
+
+            self.position += 1;
+            let right = self.expression(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expr, Error> {
+        let expected = "a number or '('";
+        let token = self.next(expected)?;
+        match token.kind {
+            TokenKind::Number(n) => Ok(Expr::Number(n)),
+            TokenKind::OpenParen => {
+                let inner = self.expression(0)?;
+                let close = self.next("')'")?;
+                match close.kind {
+                    TokenKind::CloseParen => Ok(inner),
+                    _ => Err(Error::UnexpectedToken {
+                        column: close.column,
+                        expected: "')'",
+                    }),
+                }
+            }
+            _ => Err(Error::UnexpectedToken {
+                column: token.column,
+                expected,
+            }),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
-    InvalidOperator,
-    InvalidTerm,
+    UnexpectedCharacter { column: usize, found: char },
+    NumberTooLarge { column: usize },
+    UnexpectedToken { column: usize, expected: &'static str },
+    UnexpectedEnd { column: usize, expected: &'static str },
+    DivisionByZero,
+    Overflow,
     IO,
-    UnexpectedToken(Token),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnexpectedCharacter { column, found } => {
+                write!(f, "unexpected '{}' at column {}", found, column)
+            }
+            Error::NumberTooLarge { column } => {
+                write!(f, "number at column {} is too large", column)
+            }
+            Error::UnexpectedToken { column, expected } => {
+                write!(f, "expected {} at column {}", expected, column)
+            }
+            Error::UnexpectedEnd { column, expected } => {
+                write!(f, "expected {} at column {}, found the end", expected, column)
+            }
+            Error::DivisionByZero => write!(f, "division by zero"),
+            Error::Overflow => write!(f, "result is too large"),
+            Error::IO => write!(f, "couldn't read the input"),
+        }
+    }
 }
 
 impl std::convert::From<std::io::Error> for Error {
@@ -120,6 +365,24 @@ impl std::convert::From<std::io::Error> for Error {
     }
 }
 
+#[cfg(test)]
+mod tests_part1 {
+    use super::*;
+
+    fn same(input: &str) -> Result<isize, Error> {
+        parse(input, &Precedence::same())?.evaluate()
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(Ok(71), same("1 + 2 * 3 + 4 * 5 + 6"));
+        assert_eq!(Ok(51), same("1 + (2 * 3) + (4 * (5 + 6))"));
+        assert_eq!(Ok(26), same("2 * 3 + (4 * 5)"));
+        assert_eq!(Ok(437), same("5 + (8 * 3 + 9 + 3 * 4 * 3)"));
+        assert_eq!(Ok(13632), same("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"));
+    }
+}
+
 #[cfg(test)]
 mod tests_part2 {
     use super::*;
@@ -172,3 +435,100 @@ mod tests_part2 {
         assert_eq!(Ok(480), evaluate("8 * 3 + 9 + 3 * 4"));
     }
 }
+
+#[cfg(test)]
+mod tests_expressions {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenize("12*  (345 -6)").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            vec![
+                TokenKind::Number(12),
+                TokenKind::Op(Op::Multiply),
+                TokenKind::OpenParen,
+                TokenKind::Number(345),
+                TokenKind::Op(Op::Subtract),
+                TokenKind::Number(6),
+                TokenKind::CloseParen,
+            ],
+            kinds
+        );
+        let columns: Vec<_> = tokens.iter().map(|t| t.column).collect();
+        assert_eq!(vec![1, 3, 6, 7, 11, 12, 13], columns);
+    }
+
+    #[test]
+    fn standard() {
+        let standard = Precedence::standard();
+        assert_eq!(Ok(14), parse("2 + 3 * 4", &standard).unwrap().evaluate());
+        assert_eq!(Ok(3), parse("10 - 4 - 3", &standard).unwrap().evaluate());
+        assert_eq!(Ok(5), parse("100 / 10 / 2", &standard).unwrap().evaluate());
+        assert_eq!(
+            Err(Error::DivisionByZero),
+            parse("1 / (2 - 2)", &standard).unwrap().evaluate()
+        );
+
+        let custom = Precedence::same().with(Op::Multiply, 0).with(Op::Add, 3);
+        assert_eq!(Ok(20), parse("2 * 3 + 7", &custom).unwrap().evaluate());
+        assert_eq!(1, custom.level(Op::Subtract));
+    }
+
+    #[test]
+    fn pretty() {
+        let standard = Precedence::standard();
+        let expr = parse("((1 + 2) * (3 * 4)) - (5 - (6 / 2))", &standard).unwrap();
+        assert_eq!("(1 + 2) * (3 * 4) - (5 - 6 / 2)", expr.pretty(&standard));
+
+        let addition = Precedence::addition_first();
+        let expr = parse("(1 * 2) + 3 * 4", &Precedence::same()).unwrap();
+        assert_eq!("(1 * 2) + 3 * 4", expr.pretty(&addition));
+        assert_eq!("1 * 2 + 3 * 4", expr.pretty(&Precedence::same()));
+
+        let reparsed = parse(&expr.pretty(&addition), &addition).unwrap();
+        assert_eq!(expr, reparsed);
+    }
+
+    #[test]
+    fn errors() {
+        let same = Precedence::same();
+        assert_eq!(
+            Err(Error::UnexpectedCharacter {
+                column: 5,
+                found: 'x'
+            }),
+            parse("1 + x", &same)
+        );
+        assert_eq!(
+            Err(Error::UnexpectedEnd {
+                column: 8,
+                expected: "')'"
+            }),
+            parse("(1 + 2 ", &same)
+        );
+        assert_eq!(
+            Err(Error::UnexpectedToken {
+                column: 5,
+                expected: "a number or '('"
+            }),
+            parse("1 + * 2", &same)
+        );
+        assert_eq!(
+            Err(Error::UnexpectedToken {
+                column: 3,
+                expected: "an operator"
+            }),
+            parse("1 2", &same)
+        );
+        assert_eq!(
+            Err(Error::NumberTooLarge { column: 3 }),
+            parse("1+99999999999999999999999", &same)
+        );
+        assert_eq!(
+            "expected ')' at column 8, found the end",
+            parse("(1 + 2 ", &same).unwrap_err().to_string()
+        );
+    }
+}
@@ -1,28 +1,35 @@
-use std::collections::HashMap;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io::{BufRead, BufReader};
 
 fn main() -> Result<(), Error> {
     let args: Vec<_> = std::env::args().collect();
-    let mut reader1 = BufReader::new(std::fs::File::open(&args[1])?);
-    let mut rules = Rules::parse(&mut reader1)?;
-    rules.apply_changes();
+    let part1 = args.iter().any(|a| a == "--part1");
+    let explain = args.iter().any(|a| a == "--explain");
 
-    let reader2 = BufReader::new(std::fs::File::open(&args[1])?);
-    let mut start = false;
+    let mut reader = BufReader::new(std::fs::File::open(&args[1])?);
+    let mut rules = Rules::parse(&mut reader)?;
+    if !part1 {
+        rules.apply_changes();
+    }
+
+    // Grammars without loops compile down to a regex, which is much quicker to run.
+    let compiled = rules.compile().ok();
     let mut count = 0;
-    for l in reader2.lines() {
+    for l in reader.lines() {
         let line = l?;
-        if line.is_empty() {
-            start = true;
-            continue;
-        }
+        let matched = match &compiled {
+            Some(regex) => regex.is_match(&line),
+            None => rules.matches(&line),
+        };
 
-        if !start {
-            continue;
-        }
-
-        if rules.matches(&line) {
+        if matched {
             count += 1;
+        } else if explain {
+            if let Err(mismatch) = rules.explain(&line) {
+                println!("{}: {}", line, mismatch);
+            }
         }
     }
 
@@ -35,6 +42,12 @@ fn main() -> Result<(), Error> {
 pub enum Error {
     IO(std::io::Error),
     ParseInt(std::num::ParseIntError),
+    InvalidRule(String),
+    UndefinedRule { rule: usize, used_by: usize },
+    /// The rule to start from isn't defined.
+    MissingRule(usize),
+    Recursive(usize),
+    Regex(regex::Error),
 }
 
 impl From<std::io::Error> for Error {
@@ -49,123 +62,336 @@ impl From<std::num::ParseIntError> for Error {
     }
 }
 
-#[derive(Debug)]
-pub enum Token {
-    RefList(Vec<usize>),
-    Literal(u8),
-    Or(Vec<usize>, Vec<usize>),
+impl From<regex::Error> for Error {
+    fn from(re: regex::Error) -> Self {
+        Self::Regex(re)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    Literal(String),
+    Rule(usize),
+}
+
+/// Why a message doesn't match: the furthest `position` any parse got to, and the literals
+/// that could have come next there. No literals means the message should have ended.
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub position: usize,
+    pub expected: Vec<String>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.expected.is_empty() {
+            write!(f, "expected the end of the message at position {}", self.position)
+        } else {
+            let expected: Vec<_> = self.expected.iter().map(|e| format!("\"{}\"", e)).collect();
+            write!(
+                f,
+                "expected {} at position {}",
+                expected.join(" or "),
+                self.position
+            )
+        }
+    }
 }
 
+/// A context free grammar, where each rule is any number of alternatives separated by `|`, and
+/// each alternative is a sequence of rule numbers and quoted literals. Messages are matched
+/// against rule 0.
 pub struct Rules {
-    rules: HashMap<usize, Token>,
+    rules: HashMap<usize, Vec<Vec<Symbol>>>,
 }
 
 impl Rules {
     pub fn parse<T: BufRead>(reader: &mut T) -> Result<Self, Error> {
-        let mut map = HashMap::new();
+        let mut rules = Rules {
+            rules: HashMap::new(),
+        };
         for l in reader.lines() {
             let line = l?;
             if line.is_empty() {
                 break;
             }
 
-            println!("parsing {}", line);
-
-            let (ridstr, rest) = split_once(&line, ":");
-            let rid = ridstr.parse::<usize>()?;
-            let tokens: Vec<_> = rest[1..].split(' ').collect();
-            if tokens.len() == 1 && tokens[0].starts_with('\"') {
-                let t = Token::Literal(tokens[0].bytes().nth(1).unwrap());
-                map.insert(rid, t);
-            } else {
-                let mut v1 = Vec::new();
-                let mut v2 = Vec::new();
-                let mut or = false;
-                for i in 0..tokens.len() {
-                    if or {
-                        v2.push(tokens[i].parse::<usize>().unwrap());
-                    } else if tokens[i] == "|" {
-                        or = true;
-                    } else {
-                        v1.push(tokens[i].parse::<usize>().unwrap());
-                    }
-                }
+            let (id, alternatives) = Self::parse_rule(&line)?;
+            rules.rules.insert(id, alternatives);
+        }
+
+        rules.check()?;
+        Ok(rules)
+    }
+
+    fn parse_rule(line: &str) -> Result<(usize, Vec<Vec<Symbol>>), Error> {
+        let invalid = || Error::InvalidRule(line.to_string());
+        let (id, rest) = line.split_once(':').ok_or_else(invalid)?;
+        let id = id.trim().parse::<usize>()?;
+
+        let mut alternatives = Vec::new();
+        for alternative in rest.split('|') {
+            let mut sequence = Vec::new();
+            for word in alternative.split_whitespace() {
+                let symbol = match word.strip_prefix('"') {
+                    Some(quoted) => match quoted.strip_suffix('"') {
+                        Some(literal) if !literal.is_empty() => Symbol::Literal(literal.to_string()),
+                        _ => return Err(invalid()),
+                    },
+                    None => Symbol::Rule(word.parse()?),
+                };
+                sequence.push(symbol);
+            }
+            alternatives.push(sequence);
+        }
+
+        Ok((id, alternatives))
+    }
+
+    /// Replaces (or adds) a rule, written the same way as in the input.
+    pub fn replace(&mut self, line: &str) -> Result<(), Error> {
+        let (id, alternatives) = Self::parse_rule(line)?;
+        self.rules.insert(id, alternatives);
+        self.check()
+    }
+
+    /// The loops from part 2 of the puzzle.
+    pub fn apply_changes(&mut self) {
+        self.replace("8: 42 | 42 8").unwrap();
+        self.replace("11: 42 31 | 42 11 31").unwrap();
+    }
 
-                if or {
-                    map.insert(rid, Token::Or(v1, v2));
-                } else {
-                    map.insert(rid, Token::RefList(v1));
+    fn check(&self) -> Result<(), Error> {
+        for (&used_by, alternatives) in &self.rules {
+            for symbol in alternatives.iter().flatten() {
+                if let Symbol::Rule(rule) = symbol {
+                    if !self.rules.contains_key(rule) {
+                        return Err(Error::UndefinedRule {
+                            rule: *rule,
+                            used_by,
+                        });
+                    }
                 }
             }
         }
 
-        Ok(Self{
-            rules: map,
-        })
+        Ok(())
     }
 
     pub fn matches(&self, candidate: &str) -> bool {
-        let base_rule = &self.rules[&0];
-        let results = self.matches_rule(&[candidate], base_rule);
-        println!("Finished with results: {:?}", results);
-        results.iter().any(|r| r.len() == 0)
-    }
-
-    pub fn matches_rule<'a>(&self, candidates: &[&'a str], rule: &Token) -> Vec<&'a str> {
-        let mut remainders = Vec::new();
-        for candidate in candidates {
-            println!("Matching {:?} against {}", rule, candidate);
-            match rule {
-                Token::Literal(l) => match candidate.bytes().nth(0) {
-                    Some(v) => if v == *l {
-                        println!("- succeeded");
-                        remainders.push(&candidate[1..]);
-                    } else {
-                        println!("- failed1");
-                    },
-                    None => {},
-                }
-                Token::Or(left, right) => {
-                    let mut rem1 = self.matches_list(candidates, left);
-                    remainders.append(&mut rem1);
+        self.explain(candidate).is_ok()
+    }
+
+    /// Runs an Earley parse of `candidate` from rule 0, which copes with any amount of
+    /// recursion, and reports how far it got if the message doesn't match.
+    pub fn explain(&self, candidate: &str) -> Result<(), Mismatch> {
+        let input = candidate.as_bytes();
+        let nullable = self.nullable();
+        let mut chart = Chart::new(input.len());
+        for alt in 0..self.rules.get(&0).map_or(0, Vec::len) {
+            self.add(&mut chart, 0, Item::new(0, alt, 0));
+        }
 
-                    let mut rem2 = self.matches_list(candidates, right);
-                    remainders.append(&mut rem2);
+        for i in 0..=input.len() {
+            let mut k = 0;
+            while k < chart.sets[i].len() {
+                let item = chart.sets[i][k];
+                k += 1;
+
+                match self.next_symbol(&item) {
+                    None => {
+                        let mut p = 0;
+                        while let Some(parent) = chart.waiting(item.origin, item.rule, p) {
+                            p += 1;
+                            self.add(&mut chart, i, parent.advance());
+                        }
+                    }
+                    Some(Symbol::Rule(rule)) => {
+                        for alt in 0..self.rules[rule].len() {
+                            self.add(&mut chart, i, Item::new(*rule, alt, i));
+                        }
+                        // A rule that can match nothing might already have been completed
+                        // here, before this item was waiting on it.
+                        if nullable.contains(rule) {
+                            self.add(&mut chart, i, item.advance());
+                        }
+                    }
+                    Some(Symbol::Literal(literal)) => {
+                        if input[i..].starts_with(literal.as_bytes()) {
+                            self.add(&mut chart, i + literal.len(), item.advance());
+                        }
+                    }
                 }
-                Token::RefList(vec) => {
-                    let mut rem = self.matches_list(candidates, vec);
-                    remainders.append(&mut rem);
+            }
+        }
+
+        let accepted = chart.sets[input.len()]
+            .iter()
+            .any(|item| item.rule == 0 && item.origin == 0 && self.next_symbol(item).is_none());
+        if accepted {
+            return Ok(());
+        }
+
+        let position = (0..=input.len())
+            .rev()
+            .find(|&i| !chart.sets[i].is_empty())
+            .unwrap_or(0);
+        let mut expected: Vec<String> = chart.sets[position]
+            .iter()
+            .filter_map(|item| match self.next_symbol(item) {
+                Some(Symbol::Literal(literal)) => Some(literal.clone()),
+                _ => None,
+            })
+            .collect();
+        expected.sort();
+        expected.dedup();
+
+        Err(Mismatch { position, expected })
+    }
+
+    fn add(&self, chart: &mut Chart, position: usize, item: Item) {
+        let waiting_on = match self.next_symbol(&item) {
+            Some(Symbol::Rule(rule)) => Some(*rule),
+            _ => None,
+        };
+        chart.add(position, item, waiting_on);
+    }
+
+    fn next_symbol(&self, item: &Item) -> Option<&Symbol> {
+        self.rules[&item.rule][item.alt].get(item.dot)
+    }
+
+    fn nullable(&self) -> HashSet<usize> {
+        let mut nullable = HashSet::new();
+        loop {
+            let before = nullable.len();
+            for (&id, alternatives) in &self.rules {
+                let empty = alternatives.iter().any(|alt| {
+                    alt.iter()
+                        .all(|s| matches!(s, Symbol::Rule(r) if nullable.contains(r)))
+                });
+                if empty {
+                    nullable.insert(id);
                 }
             }
+
+            if nullable.len() == before {
+                return nullable;
+            }
         }
-        remainders
     }
 
-    fn matches_list<'a>(&self, candidates: &[&'a str], list: &[usize]) -> Vec<&'a str> {
-        if list.is_empty() {
-            return candidates.to_vec();
+    /// Builds a regular expression equivalent to rule 0, which only works if no rule refers
+    /// back to itself.
+    pub fn to_regex(&self) -> Result<String, Error> {
+        let mut done = HashMap::new();
+        let mut in_progress = HashSet::new();
+        self.rule_regex(0, &mut done, &mut in_progress)
+    }
+
+    /// Compiles rule 0 into a regex that has to match the whole message.
+    pub fn compile(&self) -> Result<Regex, Error> {
+        let pattern = format!("^(?:{})$", self.to_regex()?);
+        Ok(regex::RegexBuilder::new(&pattern)
+            .size_limit(1 << 28)
+            .build()?)
+    }
+
+    fn rule_regex(
+        &self,
+        id: usize,
+        done: &mut HashMap<usize, String>,
+        in_progress: &mut HashSet<usize>,
+    ) -> Result<String, Error> {
+        if let Some(pattern) = done.get(&id) {
+            return Ok(pattern.clone());
+        }
+        if !in_progress.insert(id) {
+            return Err(Error::Recursive(id));
         }
 
-        let mut remaining = Vec::new();
-        for candidate in candidates {
-            let first = self.matches_rule(&[candidate], &self.rules[&list[0]]);
-            let mut rest = self.matches_list(&first, &list[1..]);
-            remaining.append(&mut rest);
+        let mut alternatives = Vec::new();
+        for alt in self.rules.get(&id).ok_or(Error::MissingRule(id))? {
+            let mut sequence = String::new();
+            for symbol in alt {
+                match symbol {
+                    Symbol::Literal(literal) => sequence.push_str(&regex::escape(literal)),
+                    Symbol::Rule(rule) => {
+                        sequence.push_str(&self.rule_regex(*rule, done, in_progress)?)
+                    }
+                }
+            }
+            alternatives.push(sequence);
         }
-        remaining
+
+        let pattern = match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => format!("(?:{})", alternatives.join("|")),
+        };
+        in_progress.remove(&id);
+        done.insert(id, pattern.clone());
+        Ok(pattern)
     }
+}
 
-    pub fn apply_changes(&mut self) {
-        self.rules.insert(8, Token::Or([42].to_vec(), [42, 8].to_vec()));
-        self.rules.insert(11, Token::Or([42, 31].to_vec(), [42, 11, 31].to_vec()));
+/// An Earley item: alternative `alt` of `rule`, matched up to `dot`, starting at `origin`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Item {
+    rule: usize,
+    alt: usize,
+    dot: usize,
+    origin: usize,
+}
+
+impl Item {
+    fn new(rule: usize, alt: usize, origin: usize) -> Self {
+        Item {
+            rule,
+            alt,
+            dot: 0,
+            origin,
+        }
+    }
+
+    fn advance(self) -> Self {
+        Item {
+            dot: self.dot + 1,
+            ..self
+        }
     }
 }
 
-fn split_once<'a>(in_string: &'a str, split_on: &str) -> (&'a str, &'a str) {
-    let mut splitter = in_string.splitn(2, split_on);
-    let first = splitter.next().unwrap();
-    let second = splitter.next().unwrap();
-    (first, second)
+/// The Earley sets, one for each position in the input including the end. Items are also
+/// indexed by the rule they're waiting on, so completing a rule only looks at the items that
+/// can use it.
+struct Chart {
+    sets: Vec<Vec<Item>>,
+    seen: Vec<HashSet<Item>>,
+    waiting: Vec<HashMap<usize, Vec<Item>>>,
+}
+
+impl Chart {
+    fn new(len: usize) -> Self {
+        Chart {
+            sets: vec![Vec::new(); len + 1],
+            seen: vec![HashSet::new(); len + 1],
+            waiting: vec![HashMap::new(); len + 1],
+        }
+    }
+
+    fn add(&mut self, position: usize, item: Item, waiting_on: Option<usize>) {
+        if self.seen[position].insert(item) {
+            self.sets[position].push(item);
+            if let Some(rule) = waiting_on {
+                self.waiting[position].entry(rule).or_default().push(item);
+            }
+        }
+    }
+
+    /// The `index`th item at `position` that's waiting on `rule`.
+    fn waiting(&self, position: usize, rule: usize, index: usize) -> Option<Item> {
+        self.waiting[position].get(&rule)?.get(index).copied()
+    }
 }
 
 #[cfg(test)]
@@ -316,4 +542,115 @@ mod tests_part2 {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests_grammar {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn recursion() -> Result<(), Error> {
+        // Left recursion, three alternatives and an empty one.
+        let rules = Rules::parse(&mut Cursor::new("0: 0 1 | 2 | 3 \"c\"
+1: \"a\" | \"b\" | \"ab\"
+2: \"x\"
+3: 2 2 |"))?;
+
+        assert!(rules.matches("x"));
+        assert!(rules.matches("xabba"));
+        assert!(rules.matches("c"));
+        assert!(rules.matches("xxcab"));
+        assert!(!rules.matches(""));
+        assert!(!rules.matches("xc"));
+        assert!(matches!(rules.compile(), Err(Error::Recursive(0))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn compiled() -> Result<(), Error> {
+        let rules = Rules::parse(&mut Cursor::new("0: 4 1 5
+1: 2 3 | 3 2
+2: 4 4 | 5 5
+3: 4 5 | 5 4
+4: \"a\"
+5: \"b\""))?;
+        let regex = rules.compile()?;
+
+        for message in &["ababbb", "bababa", "abbbab", "aaabbb", "aaaabbb", "aaaabb"] {
+            assert_eq!(rules.matches(message), regex.is_match(message), "{}", message);
+        }
+        assert_eq!(
+            "a(?:(?:aa|bb)(?:ab|ba)|(?:ab|ba)(?:aa|bb))b",
+            rules.to_regex()?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_rule_zero() -> Result<(), Error> {
+        let rules = Rules::parse(&mut Cursor::new("1: \"a\""))?;
+        assert!(!rules.matches("a"));
+        assert!(matches!(rules.to_regex(), Err(Error::MissingRule(0))));
+        assert!(matches!(rules.compile(), Err(Error::MissingRule(0))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn explain() -> Result<(), Error> {
+        let rules = Rules::parse(&mut Cursor::new("0: 4 1 5
+1: 2 3 | 3 2
+2: 4 4 | 5 5
+3: 4 5 | 5 4
+4: \"a\"
+5: \"b\""))?;
+
+        assert_eq!(Ok(()), rules.explain("ababbb"));
+        assert_eq!(
+            Err(Mismatch {
+                position: 0,
+                expected: vec!["a".to_string()],
+            }),
+            rules.explain("bababa")
+        );
+        assert_eq!(
+            Err(Mismatch {
+                position: 4,
+                expected: vec!["a".to_string()],
+            }),
+            rules.explain("aaabbb")
+        );
+        assert_eq!(
+            Err(Mismatch {
+                position: 6,
+                expected: vec![],
+            }),
+            rules.explain("aaaabba")
+        );
+        assert_eq!(
+            "expected \"a\" or \"b\" at position 2",
+            rules.explain("ab").unwrap_err().to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            Rules::parse(&mut Cursor::new("0: 1 2\n1: \"a\"")),
+            Err(Error::UndefinedRule { rule: 2, used_by: 0 })
+        ));
+        assert!(matches!(
+            Rules::parse(&mut Cursor::new("0: \"a")),
+            Err(Error::InvalidRule(_))
+        ));
+        assert!(matches!(
+            Rules::parse(&mut Cursor::new("0 1 2")),
+            Err(Error::InvalidRule(_))
+        ));
+    }
+}
@@ -1,68 +1,242 @@
+use regex::Regex;
+use std::fmt;
+
 #[derive(Debug)]
 pub struct PasswordLine {
-    min: i32,
-    max: i32,
+    min: usize,
+    max: usize,
     letter: char,
     password: String,
 }
 
-impl PasswordLine {
-    pub fn parse(line: &str) -> PasswordLine {
-        let mut chars = line.chars();
-        let mut start = 0;
-        let mut end = 0;
+#[derive(Debug, PartialEq)]
+pub enum ParseErrorKind {
+    ExpectedNumber,
+    NumberTooLarge,
+    Expected(char),
+    ExpectedLetter,
+}
+
+/// A parse failure, with `position` being the byte offset into the line where it happened.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub kind: ParseErrorKind,
+}
 
-        while chars.next().unwrap() != '-' {
-            end += 1;
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::ExpectedNumber => write!(f, "expected a number")?,
+            ParseErrorKind::NumberTooLarge => write!(f, "number is too large")?,
+            ParseErrorKind::Expected(c) => write!(f, "expected '{}'", c)?,
+            ParseErrorKind::ExpectedLetter => write!(f, "expected the policy letter")?,
         }
-        let min = line[start..end].parse::<i32>().unwrap();
+        write!(f, " at column {}", self.position + 1)
+    }
+}
 
-        // Skip the -
-        end += 1;
-        start = end;
+struct Cursor<'a> {
+    line: &'a str,
+    position: usize,
+}
 
-        while chars.next().unwrap() != ' ' {
-            end += 1;
+impl<'a> Cursor<'a> {
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            position: self.position,
+            kind,
         }
-        let max = line[start..end].parse::<i32>().unwrap();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.line[self.position..].chars().next()
+    }
 
-        // Skip the ' '
-        end += 1;
-        start = end;
+    fn number(&mut self) -> Result<usize, ParseError> {
+        let start = self.position;
+        while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+            self.position += c.len_utf8();
+        }
 
-        let rule_char = chars.next().unwrap();
+        if start == self.position {
+            return Err(self.error(ParseErrorKind::ExpectedNumber));
+        }
 
-        // Skip the "c: "
-        start += 3;
-        let password = &line[start..];
+        self.line[start..self.position].parse().map_err(|_| ParseError {
+            position: start,
+            kind: ParseErrorKind::NumberTooLarge,
+        })
+    }
 
-        PasswordLine {
-            min: min,
-            max: max,
-            letter: rule_char,
-            password: password.to_string(),
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += c.len_utf8();
+                Ok(())
+            }
+            _ => Err(self.error(ParseErrorKind::Expected(expected))),
         }
     }
 
-    pub fn is_valid_part1(&self) -> bool {
-        let mut count: i32 = 0;
-        for c in self.password.chars() {
-            if c == self.letter {
-                count += 1;
+    fn letter(&mut self) -> Result<char, ParseError> {
+        match self.peek() {
+            Some(c) if !c.is_whitespace() && c != ':' => {
+                self.position += c.len_utf8();
+                Ok(c)
             }
+            _ => Err(self.error(ParseErrorKind::ExpectedLetter)),
         }
+    }
+}
+
+impl PasswordLine {
+    pub fn parse(line: &str) -> Result<PasswordLine, ParseError> {
+        let mut cursor = Cursor { line, position: 0 };
+
+        let min = cursor.number()?;
+        cursor.expect('-')?;
+        let max = cursor.number()?;
+        cursor.expect(' ')?;
+        let letter = cursor.letter()?;
+        cursor.expect(':')?;
+        cursor.expect(' ')?;
+
+        Ok(PasswordLine {
+            min,
+            max,
+            letter,
+            password: line[cursor.position..].to_string(),
+        })
+    }
+
+    pub fn min(&self) -> usize {
+        self.min
+    }
 
-        count >= self.min && count <= self.max
+    pub fn max(&self) -> usize {
+        self.max
     }
 
-    pub fn is_valid_part2(&self) -> bool {
-        let charmin = self.password.chars().nth((self.min - 1) as usize).unwrap();
-        let charmax = self.password.chars().nth((self.max - 1) as usize).unwrap();
+    pub fn letter(&self) -> char {
+        self.letter
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    pub fn is_valid(&self, policy: &dyn PasswordPolicy) -> bool {
+        policy.check(self).is_ok()
+    }
+}
+
+/// A rule that a `PasswordLine` either satisfies, or fails with an explanation of why.
+pub trait PasswordPolicy {
+    fn check(&self, line: &PasswordLine) -> Result<(), String>;
+}
 
-        println!("letter: {}, min: {}, max: {}, password: {}, charmin: {}, charmax: {}",
-            self.letter, self.min, self.max, self.password, charmin, charmax);
+/// The sled rental policy: the letter must appear between `min` and `max` times.
+pub struct SledPolicy;
+
+impl PasswordPolicy for SledPolicy {
+    fn check(&self, line: &PasswordLine) -> Result<(), String> {
+        let count = line.password.chars().filter(|&c| c == line.letter).count();
+        if count >= line.min && count <= line.max {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected between {} and {} '{}', found {}",
+                line.min, line.max, line.letter, count
+            ))
+        }
+    }
+}
+
+/// The Official Toboggan Corporate policy: exactly one of the 1-based positions `min` and
+/// `max` must hold the letter.
+pub struct TobogganPolicy;
+
+impl PasswordPolicy for TobogganPolicy {
+    fn check(&self, line: &PasswordLine) -> Result<(), String> {
+        if line.min == 0 || line.max == 0 {
+            return Err("positions start at 1".to_string());
+        }
+
+        let at = |position: usize| line.password.chars().nth(position - 1) == Some(line.letter);
+        match (at(line.min), at(line.max)) {
+            (true, false) | (false, true) => Ok(()),
+            (true, true) => Err(format!(
+                "expected exactly one of positions {} and {} to be '{}', found both",
+                line.min, line.max, line.letter
+            )),
+            (false, false) => Err(format!(
+                "expected exactly one of positions {} and {} to be '{}', found neither",
+                line.min, line.max, line.letter
+            )),
+        }
+    }
+}
+
+/// A policy that matches the password against a regular expression. The placeholders
+/// `<letter>`, `<min>` and `<max>` are replaced with the values from each line before the
+/// expression is compiled, so the same pattern can adapt to every line's rule.
+pub struct PatternPolicy {
+    pattern: String,
+}
+
+impl PatternPolicy {
+    pub fn new(pattern: &str) -> PatternPolicy {
+        PatternPolicy {
+            pattern: pattern.to_string(),
+        }
+    }
+}
+
+impl PasswordPolicy for PatternPolicy {
+    fn check(&self, line: &PasswordLine) -> Result<(), String> {
+        let pattern = self
+            .pattern
+            .replace("<letter>", &regex::escape(&line.letter.to_string()))
+            .replace("<min>", &line.min.to_string())
+            .replace("<max>", &line.max.to_string());
+        let regex = Regex::new(&pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+
+        if regex.is_match(&line.password) {
+            Ok(())
+        } else {
+            Err(format!("expected to match /{}/", pattern))
+        }
+    }
+}
+
+/// Passes only if every one of the contained policies passes.
+pub struct AllOf(pub Vec<Box<dyn PasswordPolicy>>);
+
+impl PasswordPolicy for AllOf {
+    fn check(&self, line: &PasswordLine) -> Result<(), String> {
+        let failures: Vec<String> = self.0.iter().filter_map(|p| p.check(line).err()).collect();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join("; "))
+        }
+    }
+}
+
+/// Passes if at least one of the contained policies passes.
+pub struct AnyOf(pub Vec<Box<dyn PasswordPolicy>>);
+
+impl PasswordPolicy for AnyOf {
+    fn check(&self, line: &PasswordLine) -> Result<(), String> {
+        let mut failures = Vec::new();
+        for policy in &self.0 {
+            match policy.check(line) {
+                Ok(()) => return Ok(()),
+                Err(reason) => failures.push(reason),
+            }
+        }
 
-        charmin == self.letter && charmax != self.letter
-            || charmin != self.letter && charmax == self.letter
+        Err(format!("none of the policies passed: {}", failures.join("; ")))
     }
 }
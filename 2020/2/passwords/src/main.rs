@@ -1,21 +1,35 @@
+use passwords::*;
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
-use passwords::*;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let file = File::open(&args[1]);
+    let policy_name = args.iter().skip(2).find(|a| !a.starts_with("--"));
+    let policy: Box<dyn PasswordPolicy> = match policy_name.map(String::as_str) {
+        Some("sled") => Box::new(SledPolicy),
+        Some("toboggan") | None => Box::new(TobogganPolicy),
+        Some(other) => panic!("Unknown policy '{}', expected 'sled' or 'toboggan'", other),
+    };
+    let report = args.iter().any(|a| a == "--report");
 
     let reader = io::BufReader::new(file.unwrap());
     let mut total = 0;
     let mut bad = 0;
-    for line in reader.lines() {
+    for (number, line) in reader.lines().enumerate() {
         total += 1;
-        let pwl = PasswordLine::parse(&line.unwrap());
-        if !pwl.is_valid_part2() {
+        let line = line.unwrap();
+        let reason = match PasswordLine::parse(&line) {
+            Ok(pwl) => policy.check(&pwl).err(),
+            Err(e) => Some(format!("could not parse: {}", e)),
+        };
+
+        if let Some(reason) = reason {
             bad += 1;
-            println!("Found an invalid password line: {:#?}", pwl);
+            if report {
+                println!("line {}: '{}' is invalid: {}", number + 1, line, reason);
+            }
         }
     }
     println!("Found {} bad passwords out of {}, {} good", bad, total, total - bad);
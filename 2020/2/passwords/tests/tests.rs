@@ -30,12 +30,51 @@ fn third_part2() {
     assert_eq!(false, is_valid_part2(&"2-9 c: ccccccccc"));
 
 }
+
+#[test]
+fn parse_errors_have_positions() {
+    assert_eq!(
+        ParseError { position: 0, kind: ParseErrorKind::ExpectedNumber },
+        PasswordLine::parse("x-3 a: abcde").unwrap_err()
+    );
+    assert_eq!(
+        ParseError { position: 3, kind: ParseErrorKind::Expected(' ') },
+        PasswordLine::parse("1-3a: abcde").unwrap_err()
+    );
+    assert_eq!(
+        ParseError { position: 5, kind: ParseErrorKind::Expected(':') },
+        PasswordLine::parse("1-3 a abcde").unwrap_err()
+    );
+}
+
+#[test]
+fn pattern_policy() {
+    let sled = PatternPolicy::new("^([^<letter>]*<letter>){<min>,<max>}[^<letter>]*$");
+    for line in &["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc", "1-2 x: xxx"] {
+        let line = PasswordLine::parse(line).unwrap();
+        assert_eq!(line.is_valid(&SledPolicy), line.is_valid(&sled));
+    }
+}
+
+#[test]
+fn composite_policies() {
+    let line = PasswordLine::parse("2-9 c: ccccccccc").unwrap();
+    let both = AllOf(vec![Box::new(SledPolicy), Box::new(TobogganPolicy)]);
+    let either = AnyOf(vec![Box::new(SledPolicy), Box::new(TobogganPolicy)]);
+
+    assert_eq!(
+        Err("expected exactly one of positions 2 and 9 to be 'c', found both".to_string()),
+        both.check(&line)
+    );
+    assert_eq!(Ok(()), either.check(&line));
+}
+
 fn is_valid_line(line: &str) -> bool {
-    let password_line = PasswordLine::parse(line);
-    password_line.is_valid_part1()
+    let password_line = PasswordLine::parse(line).unwrap();
+    password_line.is_valid(&SledPolicy)
 }
 
 fn is_valid_part2(line: &str) -> bool {
-    let password_line = PasswordLine::parse(line);
-    password_line.is_valid_part2()
+    let password_line = PasswordLine::parse(line).unwrap();
+    password_line.is_valid(&TobogganPolicy)
 }
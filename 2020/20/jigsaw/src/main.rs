@@ -1,27 +1,56 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io::{BufRead, BufReader};
 
 fn main() -> Result<(), Error> {
     let args: Vec<_> = std::env::args().collect();
-    
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    let pattern = match option("--pattern") {
+        Some(path) => Pattern::parse(&std::fs::read_to_string(path)?),
+        None => Pattern::parse(SEA_MONSTER),
+    };
+
     let image = Image::parse(&mut BufReader::new(std::fs::File::open(&args[1])?))?;
-    let res = image.solve();
-    let product = res[0][0].id * res[0][res[0].len() - 1].id * res[res.len() - 1][0].id * res[res.len() - 1][res[res.len() - 1].len() - 1].id;
-    let (count, final_image) = Image::count_rough(&Image::assemble(&res));
-    println!("Product of corners: {}, rough seas: {}", product, count);
-
-    for row in final_image {
-        for c in row {
-            print!("{}", c as char);
+    let layout = match image.solve() {
+        Some(layout) => layout,
+        None => {
+            println!("The tiles don't fit together");
+            return Ok(());
         }
-        println!("");
+    };
+
+    let mut picture = image.assemble(&layout);
+    let found = picture.highlight(&pattern);
+    println!(
+        "Product of corners: {}, found {} matches, rough seas: {}",
+        layout.corners().iter().product::<usize>(),
+        found,
+        picture.count(b'#')
+    );
+
+    if args.iter().any(|a| a == "--show") {
+        print!("{}", picture);
+    }
+    if let Some(path) = option("--pbm") {
+        std::fs::write(path, picture.to_pbm(3))?;
     }
+
     Ok(())
 }
 
+/// The sea monster from the puzzle.
+pub const SEA_MONSTER: &str = include_str!("../sea_monster.txt");
+
 #[derive(Debug)]
 pub enum Error {
     IO(std::io::Error),
     ParseInt(std::num::ParseIntError),
+    InvalidTile(usize),
 }
 
 impl From<std::num::ParseIntError> for Error {
@@ -36,360 +65,441 @@ impl From<std::io::Error> for Error {
     }
 }
 
-pub struct Image {
-    tiles: Vec<Tile>,
+/// One of the eight ways to lay a grid down: optionally mirrored left to right, then turned
+/// clockwise by `rotation` quarter turns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orientation {
+    pub rotation: u8,
+    pub flipped: bool,
 }
 
-impl Image {
-    pub fn parse<T:BufRead>(reader:&mut T) -> Result<Self, Error> {
-        let mut tiles = Vec::new();
-        let mut curr = Vec::new();
-        let mut id = 0;
-        for l in reader.lines() {
-            let line = l?;
-            if line.is_empty() {
-                let t = Tile::new(id, curr.split_off(0));
-                println!("Created tile {:?}", t);
-                tiles.push(t);
-            } else if line.starts_with("Tile ") {
-                id = line[5..9].parse()?;
-            } else {
-                curr.push(line.clone())
-            }
-        }
-
-        Ok(Self{
-            tiles: tiles,
+impl Orientation {
+    pub fn all() -> impl Iterator<Item = Orientation> {
+        (0..8).map(|i| Orientation {
+            rotation: i % 4,
+            flipped: i >= 4,
         })
     }
 
-    pub fn solve(&self) -> Vec<Vec<Tile>> {
-        let mut tiles = self.tiles.clone();
-
-        println!("Found {} corners", tiles.iter().filter(|t| self.is_corner(t)).count());
-
-        let size = ((tiles.len() as f64).sqrt()) as usize;
-        println!("Have {} tiles, size is {}", tiles.len(), size);
-
-        let mut res = Vec::new();
-        let mut line = Vec::new();
-        println!("finding item in row 0 at 0");
-        let top_left = self.find_top_left(&mut tiles);
-        line.push(top_left);
-        for x in 1..size {
-            println!("finding item in row 0 at {}, need left of {}", x, line[x-1].right);
-            line.push(self.find_match_no_top(&mut tiles, line[x-1].right));
-        }
-        res.push(line);
-
-        for y in 1..size {
-            line = Vec::new();
-            println!("finding item in row {} at {}, need top of {}", y, 0, res[y-1][0].bottom);
-            line.push(self.find_match_no_left(&mut tiles, res[y-1][0].bottom));
-            for x in 1..size {
-                println!("finding item in row {} at {}, need top, left of {}, {}", y, x, line[x-1].right, res[y-1][x].bottom);
-                line.push(self.find_match(&mut tiles, line[x-1].right, res[y-1][x].bottom));
-            }
-            res.push(line);
+    /// The size of a `width` by `height` grid once it's been laid down this way.
+    pub fn size(self, width: usize, height: usize) -> (usize, usize) {
+        if self.rotation.is_multiple_of(2) {
+            (width, height)
+        } else {
+            (height, width)
         }
-        res
     }
 
-    fn find_top_left(&self, tiles: &mut Vec<Tile>) -> Tile {
-        let mut corner = None;
-        for i in 0..tiles.len() {
-            if self.is_corner(&tiles[i]) {
-                corner = Some(tiles.remove(i));
-                break;
-            }
-        }
-
-        let mut top_left = corner.unwrap();
-        while self.border_has_match(top_left.left, top_left.id)
-            || self.border_has_match(top_left.top, top_left.id)
-        {
-            top_left = top_left.rotate();
+    /// Where the cell at `(x, y)` in the reoriented grid comes from in the original one.
+    pub fn source(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let (sx, sy) = match self.rotation % 4 {
+            0 => (x, y),
+            1 => (y, height - 1 - x),
+            2 => (width - 1 - x, height - 1 - y),
+            _ => (width - 1 - y, x),
+        };
+
+        if self.flipped {
+            (width - 1 - sx, sy)
+        } else {
+            (sx, sy)
         }
-
-        println!("Found match: {:?}", top_left);
-        top_left
     }
+}
 
-    fn find_match_no_top(&self, tiles: &mut Vec<Tile>, left: usize) -> Tile {
-        let mut tile = None;
-        for i in 0..tiles.len() {
-            if tiles[i].has_border(left) {
-                tile = Some(tiles.remove(i));
-                break;
-            }
-        }
+/// A rectangle of cells, stored row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>,
+}
 
-        let mut res = tile.unwrap();
-        while res.left != left {
-            res = res.rotate();
+impl Grid {
+    pub fn from_lines<S: AsRef<str>>(lines: &[S]) -> Self {
+        let width = lines.iter().map(|l| l.as_ref().len()).max().unwrap_or(0);
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for line in lines {
+            let line = line.as_ref().as_bytes();
+            cells.extend_from_slice(line);
+            cells.resize(cells.len() + width - line.len(), b' ');
         }
 
-        if self.border_has_match(res.top, res.id) {
-            res = res.flip();
+        Grid {
+            width,
+            height: lines.len(),
+            cells,
         }
-        assert!(!self.border_has_match(res.top, res.id));
+    }
 
-        println!("Found match: {:?}", res);
-        res
+    pub fn width(&self) -> usize {
+        self.width
     }
 
-    fn find_match_no_left(&self, tiles: &mut Vec<Tile>, top: usize) -> Tile {
-        let mut tile = None;
-        for i in 0..tiles.len() {
-            if tiles[i].has_border(top) {
-                tile = Some(tiles.remove(i));
-                break;
-            }
-        }
+    pub fn height(&self) -> usize {
+        self.height
+    }
 
-        let mut res = tile.unwrap();
-        while self.border_has_match(res.left, res.id) {
-            res = res.rotate();
-        }
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.cells[y * self.width + x]
+    }
 
-        if res.top != top {
-            res = res.flip();
-        }
-        println!("Found match: {:?}", res);
-        assert_eq!(res.top, top);
+    /// The cell at `(x, y)` of the grid as seen in `orientation`, without copying the grid.
+    pub fn get_oriented(&self, orientation: Orientation, x: usize, y: usize) -> u8 {
+        let (sx, sy) = orientation.source(x, y, self.width, self.height);
+        self.get(sx, sy)
+    }
 
-        // println!("Found match: {:?}", res);
-        res
+    pub fn count(&self, cell: u8) -> usize {
+        self.cells.iter().filter(|&&c| c == cell).count()
     }
 
-    fn find_match(&self, tiles: &mut Vec<Tile>, left: usize, top: usize) -> Tile {
-        let mut tile = None;
-        for i in 0..tiles.len() {
-            if tiles[i].has_border(left) {
-                tile = Some(tiles.remove(i));
-                break;
+    /// Finds every place `pattern` fits over `#` cells, in any orientation, and marks the cells
+    /// it covers with `O`. Returns how many matches there were.
+    pub fn highlight(&mut self, pattern: &Pattern) -> usize {
+        // Symmetric patterns look the same in more than one orientation, and shouldn't be
+        // counted twice.
+        let mut shapes: Vec<_> = Vec::new();
+        for orientation in Orientation::all() {
+            let shape = pattern.oriented(orientation);
+            if !shapes.contains(&shape) {
+                shapes.push(shape);
             }
         }
 
-        let mut res = tile.unwrap();
-        while res.left != left {
-            res = res.rotate();
-        }
+        let mut covered = HashSet::new();
+        let mut found = 0;
+        for (width, height, points) in shapes {
+            if width > self.width || height > self.height {
+                continue;
+            }
 
-        if res.top != top {
-            res = res.flip();
+            for y in 0..=self.height - height {
+                for x in 0..=self.width - width {
+                    if points.iter().all(|(dx, dy)| self.get(x + dx, y + dy) == b'#') {
+                        found += 1;
+                        covered.extend(points.iter().map(|(dx, dy)| (x + dx, y + dy)));
+                    }
+                }
+            }
         }
 
-        println!("Found match: {:?}", res);
-        res
+        for (x, y) in covered {
+            self.cells[y * self.width + x] = b'O';
+        }
+        found
     }
 
-    pub fn assemble(tiles: &Vec<Vec<Tile>>) -> Vec<Vec<u8>> {
-        let mut res = Vec::new();
-        for ty in tiles {
-            for y in 1..9 {
-                let mut row = Vec::new();
-                for tx in ty {
-                    for x in 1..9 {
-                        row.push(tx.pixels[y][x]);
+    /// A plain PBM bitmap where each cell is a `scale` by `scale` square. Highlighted cells are
+    /// filled in and other `#` cells get a single dot in the middle, so matches stand out.
+    pub fn to_pbm(&self, scale: usize) -> String {
+        let mut out = format!("P1\n{} {}\n", self.width * scale, self.height * scale);
+        for y in 0..self.height * scale {
+            let row: Vec<&str> = (0..self.width * scale)
+                .map(|x| {
+                    let middle = x % scale == scale / 2 && y % scale == scale / 2;
+                    match self.get(x / scale, y / scale) {
+                        b'O' => "1",
+                        b'#' if middle => "1",
+                        _ => "0",
                     }
-                }
-                res.push(row);
-            }
+                })
+                .collect();
+            out.push_str(&row.join(" "));
+            out.push('\n');
         }
+        out
+    }
+}
 
-        res
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.cells.chunks(self.width.max(1)) {
+            writeln!(f, "{}", String::from_utf8_lossy(row))?;
+        }
+        Ok(())
     }
+}
 
-    pub fn count_rough(input: &Vec<Vec<u8>>) -> (usize, Vec<Vec<u8>>) {
-        let monster = "                  # 
-#    ##    ##    ###
- #  #  #  #  #  #   ";
-        let mut monster_points = Vec::new();
-        for (y, line) in monster.lines().enumerate() {
-            for (x, b) in line.bytes().enumerate() {
-                if b == b'#' {
-                    monster_points.push((y, x));
-                }
-            }
+/// A shape to look for in the assembled image. Only its `#` cells have to match; everything
+/// else is ignored.
+pub struct Pattern {
+    grid: Grid,
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Self {
+        let lines: Vec<&str> = text.lines().collect();
+        Pattern {
+            grid: Grid::from_lines(&lines),
         }
+    }
 
-        let mut image = input.clone();
-        let mut monsters = 0;
-        while monsters == 0 {
-            for y in 0..image.len() - monster.lines().count() {
-                for x in 0..image[y].len() - monster.lines().nth(0).unwrap().len() {
-                    if Self::replace_monster(&mut image, y, x, &monster_points) {
-                        monsters += 1;
-                    }
+    /// The size of the pattern in `orientation`, and the offsets of its `#` cells.
+    fn oriented(&self, orientation: Orientation) -> (usize, usize, Vec<(usize, usize)>) {
+        let (width, height) = orientation.size(self.grid.width, self.grid.height);
+        let mut points = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                if self.grid.get_oriented(orientation, x, y) == b'#' {
+                    points.push((x, y));
                 }
             }
-            image = rotate(&image);
         }
+        (width, height, points)
+    }
+}
 
-        let mut count = 0;
-        for y in 0..image.len() {
-            for x in 0..image[y].len() {
-                if image[y][x] == b'#' {
-                    count += 1;
-                }
-            }
+#[derive(Clone, Debug)]
+pub struct Tile {
+    id: usize,
+    grid: Grid,
+}
+
+impl Tile {
+    pub fn new(id: usize, lines: Vec<String>) -> Result<Self, Error> {
+        let grid = Grid::from_lines(&lines);
+        if grid.width != grid.height || grid.width < 2 || grid.count(b' ') > 0 {
+            return Err(Error::InvalidTile(id));
         }
 
-        (count, image)
+        Ok(Self { id, grid })
     }
 
-    fn replace_monster(image: &mut Vec<Vec<u8>>, y: usize, x:usize, monster_points: &Vec<(usize, usize)>) -> bool {
-        if monster_points.iter().all(|(dy, dx)| image[y + dy][x + dx] == b'#') {
-            monster_points.iter().for_each(|(dy, dx)| image[y + dy][x + dx] = b'O');
-            return true;
-        }
-        false
+    pub fn id(&self) -> usize {
+        self.id
     }
 
-    fn border_has_match(&self, border: usize, tile_id: usize) -> bool {
-        self.tiles.iter().any(|t| t.has_border(border) && t.id != tile_id)
+    /// The top, right, bottom and left edges in `orientation`, read left to right and top to
+    /// bottom, so that touching edges of neighbouring tiles are equal.
+    fn edges(&self, orientation: Orientation) -> [Vec<u8>; 4] {
+        let n = self.grid.width;
+        let cell = |x, y| self.grid.get_oriented(orientation, x, y);
+        [
+            (0..n).map(|x| cell(x, 0)).collect(),
+            (0..n).map(|y| cell(n - 1, y)).collect(),
+            (0..n).map(|x| cell(x, n - 1)).collect(),
+            (0..n).map(|y| cell(0, y)).collect(),
+        ]
+    }
+}
+
+const TOP: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const LEFT: usize = 3;
+
+/// A tile in its place, by index into the image's tiles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub tile: usize,
+    pub orientation: Orientation,
+}
+
+/// Where every tile goes, row by row.
+#[derive(Debug)]
+pub struct Layout {
+    pub width: usize,
+    pub height: usize,
+    ids: Vec<usize>,
+    cells: Vec<Placement>,
+}
+
+impl Layout {
+    pub fn get(&self, x: usize, y: usize) -> Placement {
+        self.cells[y * self.width + x]
     }
 
-    fn is_corner(&self, tile: &Tile) -> bool {
-        let matched_0 = self.border_has_match(tile.left, tile.id);
-        let matched_1 = self.border_has_match(tile.top, tile.id);
-        let matched_2 = self.border_has_match(tile.right, tile.id);
-        let matched_3 = self.border_has_match(tile.bottom, tile.id);
+    pub fn id(&self, x: usize, y: usize) -> usize {
+        self.ids[y * self.width + x]
+    }
 
-        (!matched_0 && !matched_1)
-            || (!matched_1 && !matched_2)
-            || (!matched_2 && !matched_3)
-            || (!matched_3 && !matched_0)
+    /// The ids of the corner tiles, clockwise from the top left.
+    pub fn corners(&self) -> [usize; 4] {
+        let (right, bottom) = (self.width - 1, self.height - 1);
+        [
+            self.id(0, 0),
+            self.id(right, 0),
+            self.id(right, bottom),
+            self.id(0, bottom),
+        ]
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Tile {
-    id: usize,
-    left: usize,
-    top: usize,
-    right: usize,
-    bottom: usize,
-    pixels: Vec<Vec<u8>>,
+pub struct Image {
+    tiles: Vec<Tile>,
 }
 
-impl Tile {
-    pub fn new(id: usize, lines: Vec<String>) -> Self {
-        let (top, bottom, left, right) = (
-            Self::border_id(lines[0].bytes()),
-            Self::border_id(lines[9].bytes()),
-            Self::border_id(lines.iter().map(|l| l.bytes().nth(0).unwrap())),
-            Self::border_id(lines.iter().map(|l| l.bytes().nth(9).unwrap()))
-        );
-        let mut pixels = Vec::new();
-        for l in lines {
-            let mut line = Vec::new();
-            for b in l.bytes() {
-                line.push(b);
+impl Image {
+    pub fn parse<T: BufRead>(reader: &mut T) -> Result<Self, Error> {
+        let mut tiles = Vec::new();
+        let mut curr = Vec::new();
+        let mut id = None;
+        for l in reader.lines() {
+            let line = l?;
+            if line.is_empty() {
+                if let Some(id) = id.take() {
+                    tiles.push(Tile::new(id, curr.split_off(0))?);
+                }
+            } else if let Some(rest) = line.strip_prefix("Tile ") {
+                id = Some(rest.trim_end_matches(':').parse()?);
+            } else {
+                curr.push(line)
             }
-            pixels.push(line);
         }
-        Self {
-            id: id,
-            left: left,
-            top: top,
-            right: right,
-            bottom: bottom,
-            pixels: pixels,
+        if let Some(id) = id {
+            tiles.push(Tile::new(id, curr)?);
         }
-    }
 
-    fn border_id<T: Iterator<Item=u8>>(bytes: T) -> usize {
-        let v : Vec<_> = bytes.collect();
-        let mut res1 = 0;
-        let mut res2 = 0;
-        for i in 0..v.len() {
-            if v[i] == b'#' {
-                res1 += 1 << i;
-                res2 += 1 << (v.len() - 1 - i);
-            }
+        let size = tiles.first().map(|t| t.grid.width);
+        if let Some(t) = tiles.iter().find(|t| Some(t.grid.width) != size) {
+            return Err(Error::InvalidTile(t.id));
         }
 
-        if res1 < res2 {
-            res1
-        } else {
-            res2
-        }
+        Ok(Self { tiles })
     }
 
-    fn has_border(&self, border: usize) -> bool {
-        self.left == border
-            || self.top == border
-            ||  self.right == border
-            || self.bottom == border
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
     }
 
-    fn rotate(&self) -> Self {
-        let pixels = rotate(&self.pixels);
+    /// Fits the tiles together into a rectangle, trying the shapes closest to square first.
+    /// Tiles are placed row by row, and when more than one tile fits a spot each is tried in
+    /// turn, backing up if it leads to a dead end.
+    pub fn solve(&self) -> Option<Layout> {
+        let count = self.tiles.len();
+        let mut shapes: Vec<(usize, usize)> = (1..=count)
+            .filter(|w| count.is_multiple_of(*w))
+            .map(|w| (w, count / w))
+            .collect();
+        shapes.sort_by_key(|&(w, h)| (w as isize - h as isize).abs());
+
+        let edges: Vec<Vec<[Vec<u8>; 4]>> = self
+            .tiles
+            .iter()
+            .map(|t| Orientation::all().map(|o| t.edges(o)).collect())
+            .collect();
+
+        // Every placement, indexed by its left edge and by its top edge.
+        let mut by_left: HashMap<&[u8], Vec<(usize, usize)>> = HashMap::new();
+        let mut by_top: HashMap<&[u8], Vec<(usize, usize)>> = HashMap::new();
+        for (tile, orientations) in edges.iter().enumerate() {
+            for (o, e) in orientations.iter().enumerate() {
+                by_left.entry(&e[LEFT]).or_default().push((tile, o));
+                by_top.entry(&e[TOP]).or_default().push((tile, o));
+            }
+        }
 
-        Self {
-            id: self.id,
-            left: self.top,
-            top: self.right,
-            right: self.bottom,
-            bottom: self.left,
-            pixels: pixels,
+        let orientations: Vec<Orientation> = Orientation::all().collect();
+        for (width, height) in shapes {
+            let mut search = Search {
+                width,
+                height,
+                edges: &edges,
+                by_left: &by_left,
+                by_top: &by_top,
+                used: vec![false; count],
+                placed: Vec::with_capacity(count),
+            };
+
+            if search.place() {
+                let cells: Vec<Placement> = search
+                    .placed
+                    .iter()
+                    .map(|&(tile, o)| Placement {
+                        tile,
+                        orientation: orientations[o],
+                    })
+                    .collect();
+                return Some(Layout {
+                    width,
+                    height,
+                    ids: cells.iter().map(|p| self.tiles[p.tile].id).collect(),
+                    cells,
+                });
+            }
         }
+
+        None
     }
 
-    fn flip(&self) -> Self {
-        let mut pixels = Vec::new();
-        for i in 0..self.pixels.len() {
-            pixels.push(self.pixels[self.pixels.len() - 1 - i].clone());
-        }
-        Self {
-            id: self.id,
-            left: self.left,
-            top: self.bottom,
-            right: self.right,
-            bottom: self.top,
-            pixels: pixels,
+    /// Joins the tiles in `layout` into one picture, leaving out each tile's border.
+    pub fn assemble(&self, layout: &Layout) -> Grid {
+        let inner = self.tiles[0].grid.width - 2;
+        let mut lines = Vec::new();
+        for ty in 0..layout.height {
+            for y in 1..=inner {
+                let mut row = String::new();
+                for tx in 0..layout.width {
+                    let p = layout.get(tx, ty);
+                    let grid = &self.tiles[p.tile].grid;
+                    row.extend((1..=inner).map(|x| grid.get_oriented(p.orientation, x, y) as char));
+                }
+                lines.push(row);
+            }
         }
-    }
-}
 
-pub fn rotate<T: Copy>(incoming: &Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let mut matrix = Vec::new();
-    for i in 0..incoming.len() {
-        matrix.push(incoming[i].clone());
+        Grid::from_lines(&lines)
     }
+}
 
-    let n = matrix.len();
-    for x in 0..n/2 {
-        for y in x..n-x-1 {
-            let temp = matrix[x][y];
-
-            // Move values from right to top
-            matrix[x][y] = matrix[y][n - 1 - x];
+struct Search<'a> {
+    width: usize,
+    height: usize,
+    edges: &'a [Vec<[Vec<u8>; 4]>],
+    by_left: &'a HashMap<&'a [u8], Vec<(usize, usize)>>,
+    by_top: &'a HashMap<&'a [u8], Vec<(usize, usize)>>,
+    used: Vec<bool>,
+    placed: Vec<(usize, usize)>,
+}
 
-            // Move values from bottom to right
-            matrix[y][n - 1 - x] = matrix[n - 1 - x][n - 1 - y];
+impl<'a> Search<'a> {
+    fn place(&mut self) -> bool {
+        let i = self.placed.len();
+        if i == self.width * self.height {
+            return true;
+        }
 
-            // Move values from left to bottom
-            matrix[n - 1 - x][n - 1 - y] = matrix[n - 1 - y][x];
+        let (x, y) = (i % self.width, i / self.width);
+        let left = (x > 0).then(|| self.edge(self.placed[i - 1], RIGHT));
+        let top = (y > 0).then(|| self.edge(self.placed[i - self.width], BOTTOM));
+
+        let candidates: Vec<(usize, usize)> = match (left, top) {
+            (Some(left), _) => self.by_left.get(left).cloned().unwrap_or_default(),
+            (None, Some(top)) => self.by_top.get(top).cloned().unwrap_or_default(),
+            (None, None) => (0..self.used.len())
+                .flat_map(|t| (0..8).map(move |o| (t, o)))
+                .collect(),
+        };
+
+        for (tile, o) in candidates {
+            if self.used[tile] || top.is_some_and(|top| self.edges[tile][o][TOP] != top) {
+                continue;
+            }
 
-            // Assign temp to left
-            matrix[n - 1 - y][x] = temp;
+            self.used[tile] = true;
+            self.placed.push((tile, o));
+            if self.place() {
+                return true;
+            }
+            self.placed.pop();
+            self.used[tile] = false;
         }
+
+        false
     }
 
-    matrix
+    fn edge(&self, (tile, o): (usize, usize), side: usize) -> &'a [u8] {
+        &self.edges[tile][o][side]
+    }
 }
 
 #[cfg(test)]
 mod tests_part1 {
-    use std::io::Cursor;
     use super::*;
+    use std::io::Cursor;
 
-    #[test]
-    fn test() -> Result<(), Error> {
-        let input = "Tile 2311:
+    const INPUT: &str = "Tile 2311:
 ..##.#..#.
 ##..#.....
 #...##..#.
@@ -499,12 +609,78 @@ Tile 3079:
 
 ";
 
-        let image = Image::parse(&mut Cursor::new(input))?;
-        let res = image.solve();
-        let product = res[0][0].id * res[0][res[0].len() - 1].id * res[res.len() - 1][0].id * res[res.len() - 1][res[res.len() - 1].len() - 1].id;
-        assert_eq!(20899048083289, product);
-        let (count, _) = Image::count_rough(&Image::assemble(&res));
-        assert_eq!(273, count);
+    #[test]
+    fn test() -> Result<(), Error> {
+        let image = Image::parse(&mut Cursor::new(INPUT))?;
+        let layout = image.solve().unwrap();
+        assert_eq!((3, 3), (layout.width, layout.height));
+        assert_eq!(20899048083289, layout.corners().iter().product::<usize>());
+
+        let mut picture = image.assemble(&layout);
+        assert_eq!(2, picture.highlight(&Pattern::parse(SEA_MONSTER)));
+        assert_eq!(273, picture.count(b'#'));
+        assert_eq!(30, picture.count(b'O'));
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rectangle() -> Result<(), Error> {
+        // Just the top two rows of the example, without a trailing blank line.
+        let image = Image::parse(&mut Cursor::new(INPUT.trim_end()))?;
+        let keep = [1951, 2311, 3079, 2729, 1427, 2473];
+        let image = Image {
+            tiles: image.tiles().iter().filter(|t| keep.contains(&t.id())).cloned().collect(),
+        };
+
+        let layout = image.solve().unwrap();
+        assert_eq!(6, layout.width * layout.height);
+        assert_eq!(2, layout.width.min(layout.height));
+        let mut corners = layout.corners();
+        corners.sort_unstable();
+        assert_eq!([1951, 2473, 2729, 3079], corners);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_grid {
+    use super::*;
+
+    #[test]
+    fn orientations() {
+        let grid = Grid::from_lines(&["ab", "cd", "ef"]);
+        let render = |o: Orientation| {
+            let (w, h) = o.size(grid.width(), grid.height());
+            (0..h)
+                .map(|y| (0..w).map(|x| grid.get_oriented(o, x, y) as char).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+
+        let all: Vec<String> = Orientation::all().map(render).collect();
+        assert_eq!("ab/cd/ef", all[0]);
+        assert_eq!("eca/fdb", all[1]);
+        assert_eq!("fe/dc/ba", all[2]);
+        assert_eq!("bdf/ace", all[3]);
+        assert_eq!("ba/dc/fe", all[4]);
+
+        let distinct: HashSet<&String> = all.iter().collect();
+        assert_eq!(8, distinct.len());
+    }
+
+    #[test]
+    fn pattern() {
+        let mut grid = Grid::from_lines(&["#..", "##.", "..#", "..#", ".##"]);
+        let pattern = Pattern::parse("#.\n##");
+        assert_eq!(2, grid.highlight(&pattern));
+        assert_eq!("O..\nOO.\n..#\n..O\n.OO\n", grid.to_string());
+        assert_eq!(
+            "P1\n4 4\n1 1 0 0\n1 1 0 0\n1 1 1 1\n1 1 1 1\n",
+            Grid::from_lines(&["O.", "OO"]).to_pbm(2)
+        );
+        assert_eq!(
+            "P1\n3 3\n0 0 0\n0 1 0\n0 0 0\n",
+            Grid::from_lines(&["#"]).to_pbm(3)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use assignment::Candidates;
 use std::collections::HashMap;
 use std::collections::HashSet;
 
@@ -6,8 +7,14 @@ fn main() -> Result<(), std::io::Error> {
 
     let input = std::fs::read_to_string(&args[1])?;
     let foods = FoodSet::parse_list(&input);
-    println!("Found {}", foods.count_of_non_allergens());
-    println!("Dangerous list is {}", foods.dangerous_list());
+    match foods.count_of_non_allergens() {
+        Ok(count) => println!("Found {}", count),
+        Err(e) => println!("Couldn't map the allergens: {}", e),
+    }
+    match foods.dangerous_list() {
+        Ok(list) => println!("Dangerous list is {}", list),
+        Err(e) => println!("Couldn't map the allergens: {}", e),
+    }
 
     Ok(())
 }
@@ -34,36 +41,41 @@ pub struct FoodSet<'a> {
 
 impl<'a> FoodSet<'a> {
     pub fn parse_list(input: &'a str) -> Self {
-        let foods: Vec<Food> = input.lines().map(|line| Food::new(line)).collect();
-        println!("Parsed foods:");
-        foods.iter().for_each(|f| println!("  {:?}", f));
+        let foods: Vec<Food> = input.lines().map(Food::new).collect();
         FoodSet{
-            foods: foods,
+            foods,
         }
     }
 
-    pub fn map_allergens(&self) -> HashMap<&'a str, &'a str> {
-
-        let mut all_allergens: HashSet<_> = self.foods.iter().flat_map(|f| f.allergens.iter()).map(|a| *a).collect();
-        let mut foods = self.foods.clone();
-        let mut known_allergens = HashMap::new();
-        while !all_allergens.is_empty() {
-            match match_one(&foods, &all_allergens) {
-                Some((allergen, ingredient)) => {
-                    println!("mapped {} to {}", allergen, ingredient);
-                    all_allergens.remove(&allergen);
-                    foods = clone_without_ingredient_allergen(&foods, &ingredient, &allergen);
-                    known_allergens.insert(ingredient, allergen);
-                },
-                None => panic!("Unable to match an allergen!"),
+    /// Maps each ingredient that contains an allergen to that allergen. An allergen can only
+    /// be in an ingredient that appears in every food listing it, and it's an error unless
+    /// exactly one way of matching them up remains.
+    pub fn map_allergens(&self) -> Result<HashMap<&'a str, &'a str>, assignment::Error> {
+        let mut allergens: Vec<_> = self.all_allergens().into_iter().collect();
+        allergens.sort_unstable();
+        let mut ingredients: Vec<_> = self.foods.iter().flat_map(|f| f.ingredients.iter().copied()).collect();
+        ingredients.sort_unstable();
+        ingredients.dedup();
+
+        let mut candidates = Candidates::new(allergens.len(), ingredients.len());
+        for (a, allergen) in allergens.iter().enumerate() {
+            for (i, ingredient) in ingredients.iter().enumerate() {
+                if self.foods.iter().filter(|f| f.allergens.contains(allergen)).all(|f| f.ingredients.contains(ingredient)) {
+                    candidates.allow(a, i);
+                }
             }
         }
 
-        known_allergens
+        let matched = candidates.unique()?;
+        Ok(allergens.iter().zip(matched).map(|(a, i)| (ingredients[i], *a)).collect())
+    }
+
+    fn all_allergens(&self) -> HashSet<&'a str> {
+        self.foods.iter().flat_map(|f| f.allergens.iter().copied()).collect()
     }
 
-    pub fn count_of_non_allergens(&self) -> usize {
-        let known_allergens = self.map_allergens();
+    pub fn count_of_non_allergens(&self) -> Result<usize, assignment::Error> {
+        let known_allergens = self.map_allergens()?;
         let mut res = 0;
         let all_ingredients: HashSet<_> = self.foods.iter().flat_map(|f| f.ingredients.iter()).collect();
         for i in all_ingredients {
@@ -76,58 +88,21 @@ impl<'a> FoodSet<'a> {
             }
         }
 
-        res
+        Ok(res)
     }
 
-    pub fn dangerous_list(&self) -> String {
-        let known_allergens = self.map_allergens();
-        let rev_map:HashMap<_, _>= known_allergens.iter().map(|(k,v)| (v,k)).collect();
-        let all_allergens : HashSet<_> = self.foods.iter().flat_map(|f| f.allergens.iter()).map(|s| *s).collect();
-        let mut sorted_allergens: Vec<_> = all_allergens.iter().map(|s| *s).collect();
+    pub fn dangerous_list(&self) -> Result<String, assignment::Error> {
+        let known_allergens = self.map_allergens()?;
+        let rev_map: HashMap<_, _> = known_allergens.iter().map(|(k, v)| (v, k)).collect();
+        let mut sorted_allergens: Vec<_> = self.all_allergens().into_iter().collect();
         sorted_allergens.sort();
-        let res = sorted_allergens.iter().fold("".to_string(), |acc, all| format!("{},{}", acc, rev_map[all]));
-        String::from(&res[1..])
-    }
-}
-
-fn match_one<'a>(foods: &Vec<Food<'a>>, all_allergens: &HashSet<&'a str>) -> Option<(&'a str, &'a str)> {
-    for a in all_allergens {
-        let foods_with_a: Vec<_> = foods.iter().filter(|f| f.allergens.contains(*a)).collect();
-        println!("Examining {}, amongst {:?}", a, foods_with_a);
-        let mut possible_ingredients: HashSet<_> = foods_with_a[0].ingredients.clone();
-        for i in 1..foods_with_a.len() {
-            possible_ingredients = possible_ingredients.intersection(&foods_with_a[i].ingredients).map(|f| *f).collect();
-        }
-
-        if possible_ingredients.len() == 1 {
-            return Some((*a, possible_ingredients.iter().nth(0).unwrap()));
-        }
-    }
-
-    None
-}
-
-fn clone_without_ingredient_allergen<'a>(foods: &Vec<Food<'a>>, ingredient: &str, allergen: &str) -> Vec<Food<'a>> {
-    let mut res = Vec::new();
-    for f in foods {
-        let mut ingredients = f.ingredients.clone();
-        let mut allergens = f.allergens.clone();
-        ingredients.remove(ingredient);
-        allergens.remove(allergen);
-
-        res.push(Food {
-            ingredients: ingredients,
-            allergens: allergens,
-        });
+        let res: Vec<_> = sorted_allergens.iter().map(|a| *rev_map[a]).collect();
+        Ok(res.join(","))
     }
-    res
 }
 
 fn split_once<'a>(in_string: &'a str, split_on: &str) -> (&'a str, &'a str) {
-    let mut splitter = in_string.splitn(2, split_on);
-    let first = splitter.next().unwrap();
-    let second = splitter.next().unwrap();
-    (first, second)
+    in_string.split_once(split_on).unwrap()
 }
 
 #[cfg(test)]
@@ -140,7 +115,7 @@ mod tests_part1 {
 trh fvjkl sbzzf mxmxvkd (contains dairy)
 sqjhc fvjkl (contains soy)
 sqjhc mxmxvkd sbzzf (contains fish)");
-        assert_eq!(5, food_set.count_of_non_allergens());
-        assert_eq!("mxmxvkd,sqjhc,fvjkl", food_set.dangerous_list());
+        assert_eq!(Ok(5), food_set.count_of_non_allergens());
+        assert_eq!(Ok("mxmxvkd,sqjhc,fvjkl".to_string()), food_set.dangerous_list());
     }
 }
\ No newline at end of file
@@ -1,111 +1,100 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
 fn main() {
-    let args:Vec<_> = std::env::args().collect();
+    let args: Vec<_> = std::env::args().collect();
     let input = std::fs::read_to_string(&args[1]).unwrap();
+    let recursive = !args.iter().any(|a| a == "--part1");
     let game = GameState::parse(&input);
 
-    let (p1_wins, score) = GameState::play_part_2(&game, 1);
-    println!("The winner's score was '{}', and p1 was winner: '{}'.", score, p1_wins);
+    let mut engine = if args.iter().any(|a| a == "--log") {
+        Engine::with_log()
+    } else {
+        Engine::new()
+    };
+    let outcome = engine.play(&game, recursive);
+    if let Some(log) = engine.log() {
+        print!("{}", log);
+    }
+    println!(
+        "The winner's score was '{}', and the winner was player {}.",
+        outcome.score,
+        outcome.winner + 1
+    );
 }
 
-#[derive(Clone)]
+/// Everyone's decks, top card first. Players whose decks are empty are out of the game.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GameState {
-    player1: Vec<usize>,
-    player2: Vec<usize>,
-
+    decks: Vec<VecDeque<usize>>,
 }
 
 impl GameState {
+    /// Reads any number of decks, each under its own "Player N:" heading.
     pub fn parse(input: &str) -> Self {
-        let mut p1 = Vec::new();
-        let mut p2 = Vec::new();
-        let mut saw_blank = false;
+        let mut decks: Vec<VecDeque<usize>> = Vec::new();
         for line in input.lines() {
             if line.starts_with("Player") {
-                continue;
-            } else if line.is_empty() {
-                saw_blank = true;
-            } else if saw_blank {
-                p2.push(line.parse::<usize>().unwrap());
-            } else {
-                p1.push(line.parse::<usize>().unwrap());
+                decks.push(VecDeque::new());
+            } else if !line.is_empty() {
+                decks
+                    .last_mut()
+                    .expect("cards should come after a player heading")
+                    .push_back(line.parse::<usize>().unwrap());
             }
         }
-        Self {
-            player1: p1,
-            player2: p2,
-        }
+        Self { decks }
     }
 
-    pub fn play_round(&self) -> Self {
-        let p1 = self.player1[0] > self.player2[0];
-        // println!("Player 1 was winner: {}", p1);
-        self.new_with_winner(p1)
+    pub fn players(&self) -> usize {
+        self.decks.len()
     }
 
-    fn new_with_winner(&self, p1_wins: bool) -> Self {
-            let mut p1 = self.player1[1..].to_vec();
-        let mut p2 = self.player2[1..].to_vec();
-
-        if p1_wins {
-            p1.push(self.player1[0]);
-            p1.push(self.player2[0]);
-        } else {
-            p2.push(self.player2[0]);
-            p2.push(self.player1[0]);
-        }
-
-        Self {
-            player1: p1,
-            player2: p2,
-        }
+    pub fn deck(&self, player: usize) -> Vec<usize> {
+        self.decks[player].iter().copied().collect()
     }
 
-    pub fn play_part_2(start: &Self, game: usize) -> (bool, usize)
-    {
-        // println!("=== Game {} ===", game);
-        let mut previous = Vec::new();
-        let mut state = start.clone();
-        while !(state.player1.is_empty() || state.player2.is_empty()) {
-            // println!("-- Round {} (Game {}) --", previous.len() + 1, game);
-            // println!("Player 1's deck: {:?}", state.player1);
-            // println!("Player 2's deck: {:?}", state.player2);
+    /// The players who still have cards.
+    fn remaining(&self) -> Vec<usize> {
+        (0..self.decks.len())
+            .filter(|&p| !self.decks[p].is_empty())
+            .collect()
+    }
 
-            if previous.iter().any(|g| state.matches(g)) {
-                println!("Player 1 wins because game was seen!");
-                return (true, Self::score(&state.player1))
-            }
+    fn is_over(&self) -> bool {
+        self.decks.iter().filter(|d| !d.is_empty()).count() <= 1
+    }
 
-            previous.push(state.clone());
-
-            if state.player1[0] < state.player1.len()
-                && state.player2[0] < state.player2.len()
-            {
-                // play recursive game
-                let subp1 = &state.player1[1..state.player1[0]+1];
-                let subp2 = &state.player2[1..state.player2[0]+1];
-                let subgame = Self {
-                    player1: subp1.to_vec(),
-                    player2: subp2.to_vec(),
-                };
-
-                // println!("Playing a sub-game to decide");
-                let (p1, _) = Self::play_part_2(&subgame, game + 1);
-                state = state.new_with_winner(p1);
-                println!("Player 1 won sub-game: {}", p1);
-            } else {
-                state = state.play_round();
-            };
-        }
+    /// Plays one round of regular Combat, where the highest card wins.
+    pub fn play_round(&self) -> Self {
+        let mut next = self.clone();
+        let played = next.draw();
+        let winner = played.iter().max_by_key(|(_, card)| *card).unwrap().0;
+        next.collect(winner, &played);
+        next
+    }
 
-        match state.player1.is_empty() {
-            true => (false, Self::score(&state.player2)),
-            false => (true, Self::score(&state.player1)),
-        }
+    /// Everyone still in the game plays their top card.
+    fn draw(&mut self) -> Vec<(usize, usize)> {
+        self.decks
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(p, deck)| Some((p, deck.pop_front()?)))
+            .collect()
     }
 
-    fn matches(&self, other: &GameState) -> bool {
-        sequence_equal(&self.player1, &other.player1) 
-        && sequence_equal(&self.player2, &other.player2)
+    /// The winner puts their own card on the bottom of their deck first, then everyone
+    /// else's from highest to lowest. With two players that's the puzzle's rule.
+    fn collect(&mut self, winner: usize, played: &[(usize, usize)]) {
+        let own = played.iter().find(|(p, _)| *p == winner).unwrap().1;
+        self.decks[winner].push_back(own);
+        let mut others: Vec<usize> = played
+            .iter()
+            .filter(|(p, _)| *p != winner)
+            .map(|(_, card)| *card)
+            .collect();
+        others.sort_unstable_by(|a, b| b.cmp(a));
+        self.decks[winner].extend(others);
     }
 
     pub fn score(cards: &[usize]) -> usize {
@@ -117,18 +106,145 @@ impl GameState {
     }
 }
 
-pub fn sequence_equal<T: PartialEq>(left: &[T], right: &[T]) -> bool {
-    if left.len() != right.len() {
-        return false;
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub winner: usize,
+    pub score: usize,
+}
+
+/// Plays games of Combat, remembering who won each sub-game of Recursive Combat so the same
+/// decks are never played out twice. With a log, it writes the same transcript as the puzzle
+/// does, and plays every sub-game out so the transcript is complete.
+#[derive(Default)]
+pub struct Engine {
+    winners: HashMap<GameState, usize>,
+    log: Option<String>,
+    games: usize,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_log() -> Self {
+        Engine {
+            log: Some(String::new()),
+            ..Self::default()
+        }
+    }
+
+    pub fn log(&self) -> Option<&str> {
+        self.log.as_deref()
+    }
+
+    pub fn play(&mut self, start: &GameState, recursive: bool) -> Outcome {
+        let (winner, state) = self.play_game(start.clone(), recursive);
+        self.note(format_args!("\n== Post-game results ==\n"));
+        for p in 0..state.players() {
+            self.note(format_args!(
+                "Player {}'s deck: {}\n",
+                p + 1,
+                join(&state.decks[p])
+            ));
+        }
+
+        Outcome {
+            winner,
+            score: GameState::score(&state.deck(winner)),
+        }
     }
 
-    for i in 0..left.len() {
-        if left[i] != right[i] {
-            return false;
+    fn note(&mut self, args: std::fmt::Arguments) {
+        if let Some(log) = &mut self.log {
+            log.write_fmt(args).unwrap();
         }
     }
 
-    true
+    fn play_game(&mut self, mut state: GameState, recursive: bool) -> (usize, GameState) {
+        self.games += 1;
+        let game = self.games;
+        self.note(format_args!("=== Game {} ===\n", game));
+
+        let mut seen = HashSet::new();
+        let mut round = 0;
+        while !state.is_over() {
+            // A repeated position would repeat forever, so it goes to the first player left.
+            if recursive && !seen.insert(state.clone()) {
+                let winner = state.remaining()[0];
+                return (winner, state);
+            }
+
+            round += 1;
+            if self.log.is_some() {
+                self.note(format_args!("\n-- Round {} (Game {}) --\n", round, game));
+                for p in state.remaining() {
+                    self.note(format_args!(
+                        "Player {}'s deck: {}\n",
+                        p + 1,
+                        join(&state.decks[p])
+                    ));
+                }
+            }
+
+            let played = state.draw();
+            for (p, card) in &played {
+                self.note(format_args!("Player {} plays: {}\n", p + 1, card));
+            }
+
+            let recurse = recursive && played.iter().all(|&(p, card)| state.decks[p].len() >= card);
+            let winner = if recurse {
+                self.sub_game(&state, &played, game)
+            } else {
+                played.iter().max_by_key(|(_, card)| *card).unwrap().0
+            };
+
+            self.note(format_args!(
+                "Player {} wins round {} of game {}!\n",
+                winner + 1,
+                round,
+                game
+            ));
+            state.collect(winner, &played);
+        }
+
+        let winner = state.remaining()[0];
+        if game > 1 {
+            self.note(format_args!(
+                "The winner of game {} is player {}!\n",
+                game,
+                winner + 1
+            ));
+        }
+        (winner, state)
+    }
+
+    fn sub_game(&mut self, state: &GameState, played: &[(usize, usize)], game: usize) -> usize {
+        let mut decks = vec![VecDeque::new(); state.players()];
+        for &(p, card) in played {
+            decks[p] = state.decks[p].iter().take(card).copied().collect();
+        }
+        let sub = GameState { decks };
+
+        if self.log.is_none() {
+            if let Some(&winner) = self.winners.get(&sub) {
+                return winner;
+            }
+        }
+
+        self.note(format_args!(
+            "Playing a sub-game to determine the winner...\n\n"
+        ));
+        let (winner, _) = self.play_game(sub.clone(), true);
+        self.note(format_args!("\n...anyway, back to game {}.\n", game));
+        self.winners.insert(sub, winner);
+        winner
+    }
+}
+
+fn join(cards: &VecDeque<usize>) -> String {
+    let cards: Vec<String> = cards.iter().map(|c| c.to_string()).collect();
+    cards.join(", ")
 }
 
 #[cfg(test)]
@@ -137,7 +253,8 @@ mod tests_part1 {
 
     #[test]
     fn test() {
-        let mut game = GameState::parse("Player 1:
+        let mut game = GameState::parse(
+            "Player 1:
 9
 2
 6
@@ -149,35 +266,36 @@ Player 2:
 8
 4
 7
-10");
+10",
+        );
 
         game = game.play_round();
-        assert_all(&[2, 6, 3, 1, 9, 5], &game.player1);
-        assert_all(&[8, 4, 7, 10], &game.player2);
+        assert_all(&[2, 6, 3, 1, 9, 5], &game.deck(0));
+        assert_all(&[8, 4, 7, 10], &game.deck(1));
 
         game = game.play_round();
-        assert_all(&[6, 3, 1, 9, 5], &game.player1);
-        assert_all(&[4, 7, 10, 8, 2], &game.player2);
+        assert_all(&[6, 3, 1, 9, 5], &game.deck(0));
+        assert_all(&[4, 7, 10, 8, 2], &game.deck(1));
 
         game = game.play_round();
-        assert_all(&[3, 1, 9, 5, 6, 4], &game.player1);
-        assert_all(&[7, 10, 8, 2], &game.player2);
+        assert_all(&[3, 1, 9, 5, 6, 4], &game.deck(0));
+        assert_all(&[7, 10, 8, 2], &game.deck(1));
 
         game = game.play_round();
-        assert_all(&[1, 9, 5, 6, 4], &game.player1);
-        assert_all(&[10, 8, 2, 7, 3], &game.player2);
+        assert_all(&[1, 9, 5, 6, 4], &game.deck(0));
+        assert_all(&[10, 8, 2, 7, 3], &game.deck(1));
 
-        while !(game.player1.is_empty() || game.player2.is_empty()) {
+        while !game.is_over() {
             game = game.play_round();
         }
 
-        assert!(game.player1.is_empty());
-        assert_all(&[3, 2, 10, 6, 8, 5, 9, 4, 7, 1], &game.player2);
+        assert!(game.deck(0).is_empty());
+        assert_all(&[3, 2, 10, 6, 8, 5, 9, 4, 7, 1], &game.deck(1));
 
-        assert_eq!(306, GameState::score(&game.player2));
+        assert_eq!(306, GameState::score(&game.deck(1)));
     }
 
-    fn assert_all<T: std::fmt::Debug+PartialEq>(expected: &[T], actual: &[T]) {
+    fn assert_all<T: std::fmt::Debug + PartialEq>(expected: &[T], actual: &[T]) {
         assert_eq!(expected.len(), actual.len());
         for i in 0..expected.len() {
             assert_eq!(expected[i], actual[i]);
@@ -191,7 +309,8 @@ mod tests_part2 {
 
     #[test]
     fn test() {
-        let game = GameState::parse("Player 1:
+        let game = GameState::parse(
+            "Player 1:
 9
 2
 6
@@ -203,9 +322,123 @@ Player 2:
 8
 4
 7
-10");
+10",
+        );
+
+        let outcome = Engine::new().play(&game, true);
+        assert_eq!(
+            Outcome {
+                winner: 1,
+                score: 291
+            },
+            outcome
+        );
+    }
 
-        let (_, score) = GameState::play_part_2(&game, 1);
-        assert_eq!(291, score);
+    #[test]
+    fn log() {
+        let game = GameState::parse(
+            "Player 1:
+9
+2
+6
+3
+1
+
+Player 2:
+5
+8
+4
+7
+10",
+        );
+
+        let mut engine = Engine::with_log();
+        engine.play(&game, true);
+        let log = engine.log().unwrap();
+        assert!(log.starts_with(
+            "=== Game 1 ===
+
+-- Round 1 (Game 1) --
+Player 1's deck: 9, 2, 6, 3, 1
+Player 2's deck: 5, 8, 4, 7, 10
+Player 1 plays: 9
+Player 2 plays: 5
+Player 1 wins round 1 of game 1!
+"
+        ));
+        assert!(log.contains(
+            "-- Round 9 (Game 1) --
+Player 1's deck: 4, 9, 8, 5, 2
+Player 2's deck: 3, 10, 1, 7, 6
+Player 1 plays: 4
+Player 2 plays: 3
+Playing a sub-game to determine the winner...
+
+=== Game 2 ===
+"
+        ));
+        assert!(log.contains(
+            "The winner of game 2 is player 2!
+
+...anyway, back to game 1.
+Player 2 wins round 9 of game 1!
+"
+        ));
+        assert!(log.ends_with(
+            "== Post-game results ==
+Player 1's deck: 
+Player 2's deck: 7, 5, 6, 2, 4, 1, 10, 8, 9, 3
+"
+        ));
+        assert_eq!(5, log.matches("=== Game").count());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn repeats() {
+        let game = GameState::parse(
+            "Player 1:
+43
+19
+
+Player 2:
+2
+29
+14",
+        );
+
+        assert_eq!(0, Engine::new().play(&game, true).winner);
+    }
+
+    #[test]
+    fn three_players() {
+        let game = GameState::parse(
+            "Player 1:
+1
+9
+
+Player 2:
+5
+2
+
+Player 3:
+3
+4",
+        );
+
+        // 5 beats 1 and 3, then 9 beats 2 and 4 and knocks player 3 out.
+        let state = game.play_round().play_round();
+        assert_eq!(vec![9, 4, 2], state.deck(0));
+        assert_eq!(vec![5, 3, 1], state.deck(1));
+        assert!(state.deck(2).is_empty());
+
+        let outcome = Engine::new().play(&game, false);
+        assert_eq!(
+            Outcome {
+                winner: 0,
+                score: 109
+            },
+            outcome
+        );
+    }
+}
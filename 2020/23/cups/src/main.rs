@@ -1,136 +1,194 @@
-use std::cmp::min;
-use std::collections::HashMap;
-use std::ops::RangeInclusive;
-
-fn main() {
-    let mut g = GameState::new_part2("523764819");
-    for _ in 0..10_000_000 {
+use std::fmt;
+
+fn main() -> Result<(), Error> {
+    let args: Vec<_> = std::env::args().collect();
+    let option = |name: &str, default: usize| match args.iter().position(|a| a == name) {
+        Some(i) => args[i + 1].parse().expect("options should be numbers"),
+        None => default,
+    };
+
+    let labels = parse_labels(&args[1])?;
+    let total = option("--cups", 1_000_000);
+    let moves = option("--moves", 10_000_000);
+    let mut g = GameState::new(&labels, total, option("--pick-up", 3))?;
+    for _ in 0..moves {
         g.play_round();
     }
-    println!("{}", g.products());
+
+    if total <= 100 {
+        println!("{}", g.order_after_one());
+    } else {
+        println!("{}", g.products());
+    }
+
+    Ok(())
 }
 
-pub struct GameState {
-    cups: HashMap<usize, usize>,
-    current_cup: usize,
-    move_number: usize,
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    InvalidLabel(String),
+    LabelOutOfRange(u32),
+    DuplicateLabel(u32),
+    TooFewCups,
 }
 
-impl GameState {
-    pub fn new(input: &str) -> Self {
-        let mut cups = HashMap::new();
-        let first = input[0..1].parse::<usize>().unwrap();
-        let mut cur = first;
-        for c in input[1..].chars() {
-            let cc = String::from(c).parse::<usize>().unwrap();
-            cups.insert(cur, cc);
-            cur = cc;
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidLabel(label) => write!(f, "'{}' isn't a cup label", label),
+            Error::LabelOutOfRange(label) => write!(f, "there's no room for a cup labelled {}", label),
+            Error::DuplicateLabel(label) => write!(f, "more than one cup is labelled {}", label),
+            Error::TooFewCups => write!(f, "there aren't enough cups to pick up that many"),
         }
-        cups.insert(cur, first);
+    }
+}
 
-        Self {
-            cups: cups,
-            current_cup: first,
-            move_number: 1,
-        }
+/// Reads starting labels written either as a run of single digits like `389125467`, or
+/// separated by commas when some are larger than 9.
+pub fn parse_labels(input: &str) -> Result<Vec<u32>, Error> {
+    let input = input.trim();
+    let parse = |s: &str| s.trim().parse().map_err(|_| Error::InvalidLabel(s.to_string()));
+    if input.contains(',') {
+        input.split(',').map(parse).collect()
+    } else {
+        input
+            .char_indices()
+            .map(|(i, c)| parse(&input[i..i + c.len_utf8()]))
+            .collect()
     }
+}
 
-    pub fn new_part2(input: &str) -> Self {
-        let mut cups = HashMap::new();
-        let first = input[0..1].parse::<usize>().unwrap();
-        let mut cur = first;
-        for c in input[1..].chars() {
-            let cc = String::from(c).parse::<usize>().unwrap();
-            println!("parsing {}->{}", cur, cc);
-            cups.insert(cur, cc);
-            cur = cc;
+/// The ring of cups, stored as the label of the cup clockwise of each cup, indexed by label.
+/// Index 0 is unused since labels start at 1.
+pub struct GameState {
+    next: Vec<u32>,
+    current_cup: u32,
+    pick_up: usize,
+    picked: Vec<u32>,
+}
+
+impl GameState {
+    /// A ring of `total` cups, starting with `labels` in order and then every label they skip,
+    /// counting up from 1. Each move picks up `pick_up` cups.
+    pub fn new(labels: &[u32], total: usize, pick_up: usize) -> Result<Self, Error> {
+        let total = total.max(labels.len());
+        if total < pick_up + 2 {
+            return Err(Error::TooFewCups);
         }
 
-        for cc in cups.len()+2..1_000_001 {
-            cups.insert(cur, cc);
-            cur = cc;
+        let mut seen = vec![false; total + 1];
+        for &label in labels {
+            if label == 0 || label as usize > total {
+                return Err(Error::LabelOutOfRange(label));
+            }
+            if seen[label as usize] {
+                return Err(Error::DuplicateLabel(label));
+            }
+            seen[label as usize] = true;
         }
-        println!("linking {}->{}", cur, first);
-        cups.insert(cur, first);
 
-        Self {
-            cups: cups,
-            current_cup: first,
-            move_number: 1,
+        let order = labels
+            .iter()
+            .copied()
+            .chain((1..=total as u32).filter(|&l| !seen[l as usize]));
+        let mut next = vec![0; total + 1];
+        let mut first = None;
+        let mut prev = 0;
+        for label in order {
+            if first.is_none() {
+                first = Some(label);
+            } else {
+                next[prev as usize] = label;
+            }
+            prev = label;
         }
+        let first = first.unwrap();
+        next[prev as usize] = first;
+
+        Ok(Self {
+            next,
+            current_cup: first,
+            pick_up,
+            picked: Vec::with_capacity(pick_up),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.next.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     pub fn play_round(&mut self) {
-        // println!("-- move {} --", self.move_number);
-        // self.print_state();
-
-        let mut pickup_cups = Vec::new();
-        let mut cup = self.cups[&self.current_cup];
-
-        // print!("pick up: ");
-        for _ in 0..3 {
-            // print!("{} ", cup);
-            pickup_cups.push(cup);
-            cup = self.cups[&cup];
-       }
-    //    println!("");
-
-        let mut destination = decrement_wrap(self.current_cup, &RangeInclusive::new(1, self.cups.len()));
-        while pickup_cups.contains(&destination) {
-            destination = decrement_wrap(destination, &RangeInclusive::new(1, self.cups.len())); 
+        self.picked.clear();
+        let mut cup = self.next[self.current_cup as usize];
+        for _ in 0..self.pick_up {
+            self.picked.push(cup);
+            cup = self.next[cup as usize];
         }
 
-        // println!("destination: {}", destination);
+        let total = self.len() as u32;
+        let mut destination = self.current_cup;
+        loop {
+            destination = if destination == 1 { total } else { destination - 1 };
+            if !self.picked.contains(&destination) {
+                break;
+            }
+        }
 
-        self.cups.insert(self.current_cup, self.cups[pickup_cups.last().unwrap()]);
-        self.cups.insert(*pickup_cups.last().unwrap(), self.cups[&destination]);
-        self.cups.insert(destination, pickup_cups[0]);
+        let first = self.picked[0];
+        let last = *self.picked.last().unwrap();
+        self.next[self.current_cup as usize] = cup;
+        self.next[last as usize] = self.next[destination as usize];
+        self.next[destination as usize] = first;
 
-        self.current_cup = self.cups[&self.current_cup];
-        self.move_number += 1;
+        self.current_cup = cup;
     }
 
-    fn _print_state(&self) {
-        print!("cups: ");
-        let mut cup = self.current_cup;
-        print!("({})", cup);
-        cup = self.cups[&cup];
-        for _ in 1..min(self.cups.len() -1, 15) {
-            print!(" {} ", cup);
-            cup = self.cups[&cup];
-        }
-        println!("");
+    pub fn current(&self) -> usize {
+        self.current_cup as usize
     }
 
-    pub fn current(&self) -> usize {
-        self.current_cup
+    /// Every cup once, clockwise, starting with `cup`.
+    pub fn iter_from(&self, cup: u32) -> Ring<'_> {
+        Ring {
+            next: &self.next,
+            cup,
+            remaining: self.len(),
+        }
     }
 
     pub fn order_after_one(&self) -> String {
-        let mut res = String::from("");
-        let mut cur = self.cups[&1];
-        for _ in 0..self.cups.len() - 1
-        {
-            res = format!("{}{}", res, cur);
-            cur = self.cups[&cur];
-        }
-        res
+        self.iter_from(1).skip(1).map(|c| c.to_string()).collect()
     }
 
     pub fn products(&self) -> usize {
-        let c = self.cups[&1];
-        c * self.cups[&c]
+        self.iter_from(1).skip(1).take(2).map(|c| c as usize).product()
     }
 }
 
-fn decrement_wrap(val: usize, bounds: &RangeInclusive<usize>) -> usize {
-    if &val == bounds.start() {
-        *bounds.end()
-    } else {
-        val - 1
-    }
+pub struct Ring<'a> {
+    next: &'a [u32],
+    cup: u32,
+    remaining: usize,
 }
 
+impl<'a> Iterator for Ring<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        let cup = self.cup;
+        self.cup = self.next[cup as usize];
+        Some(cup)
+    }
+}
 
 #[cfg(test)]
 mod tests_part1 {
@@ -138,7 +196,7 @@ mod tests_part1 {
 
     #[test]
     fn test() {
-        let mut state = GameState::new("389125467");
+        let mut state = GameState::new(&parse_labels("389125467").unwrap(), 9, 3).unwrap();
         // assert_all(&mut [3, 8, 9, 1, 2, 5, 4, 6, 7].iter(), &mut state.cups.iter());
         assert_eq!(3, state.current());
 
@@ -191,12 +249,47 @@ mod tests_part2 {
 
     #[test]
     fn test() {
-        let mut state = GameState::new_part2("389125467");
-        state._print_state();
+        let mut state = GameState::new(&parse_labels("389125467").unwrap(), 1_000_000, 3).unwrap();
         for _ in 0..10_000_000 {
             state.play_round();
         }
 
         assert_eq!(149245887792, state.products());
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests_ring {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        assert_eq!(Ok(vec![3, 8, 9]), parse_labels("389\n"));
+        assert_eq!(Ok(vec![12, 3, 10]), parse_labels("12, 3,10"));
+        assert_eq!(Err(Error::InvalidLabel("x".to_string())), parse_labels("3x"));
+
+        assert!(matches!(GameState::new(&[1, 2, 2], 5, 3), Err(Error::DuplicateLabel(2))));
+        assert!(matches!(GameState::new(&[0], 5, 3), Err(Error::LabelOutOfRange(0))));
+        assert!(matches!(GameState::new(&[7], 5, 3), Err(Error::LabelOutOfRange(7))));
+        assert!(matches!(GameState::new(&[1, 2, 3], 4, 3), Err(Error::TooFewCups)));
+    }
+
+    #[test]
+    fn ring() {
+        let state = GameState::new(&[3, 1], 5, 3).unwrap();
+        assert_eq!(5, state.len());
+        assert_eq!(vec![3, 1, 2, 4, 5], state.iter_from(3).collect::<Vec<_>>());
+        assert_eq!(vec![4, 5, 3, 1, 2], state.iter_from(4).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pick_up() {
+        // Picking up one cup at a time: 3 moves 8 behind 2, then 9 moves 1 behind 8.
+        let mut state = GameState::new(&parse_labels("389125467").unwrap(), 9, 1).unwrap();
+        state.play_round();
+        assert_eq!(vec![3, 9, 1, 2, 8, 5, 4, 6, 7], state.iter_from(3).collect::<Vec<_>>());
+        assert_eq!(9, state.current());
+        state.play_round();
+        assert_eq!(vec![9, 2, 8, 1, 5, 4, 6, 7, 3], state.iter_from(9).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,344 @@
+//! Hexagonal grids. Positions are axial coordinates `(q, r)`, with `r` increasing southwards;
+//! cube coordinates add the redundant `s = -q - r` for the maths that's simpler with three
+//! axes. The same coordinates work for pointy-topped and flat-topped hexes, which only differ
+//! in which compass directions the six neighbours lie in and in how they're drawn.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Top {
+    Pointy,
+    Flat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Axial {
+    pub q: isize,
+    pub r: isize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Cube {
+    pub q: isize,
+    pub r: isize,
+    pub s: isize,
+}
+
+/// The six neighbouring offsets, anticlockwise from `(1, 0)`.
+pub const NEIGHBOURS: [Axial; 6] = [
+    Axial::new(1, 0),
+    Axial::new(1, -1),
+    Axial::new(0, -1),
+    Axial::new(-1, 0),
+    Axial::new(-1, 1),
+    Axial::new(0, 1),
+];
+
+impl Axial {
+    pub const fn new(q: isize, r: isize) -> Self {
+        Axial { q, r }
+    }
+
+    pub fn to_cube(self) -> Cube {
+        Cube {
+            q: self.q,
+            r: self.r,
+            s: -self.q - self.r,
+        }
+    }
+
+    pub fn neighbours(self) -> [Axial; 6] {
+        NEIGHBOURS.map(|d| self + d)
+    }
+
+    /// The neighbour in `direction`, if there's a hex that way with this top.
+    pub fn step(self, direction: Direction, top: Top) -> Option<Axial> {
+        direction.delta(top).map(|d| self + d)
+    }
+
+    pub fn distance(self, other: Axial) -> usize {
+        let d = (self - other).to_cube();
+        (d.q.unsigned_abs() + d.r.unsigned_abs() + d.s.unsigned_abs()) / 2
+    }
+
+    /// The hexes exactly `radius` steps away, going anticlockwise around from the one
+    /// `radius` steps south west.
+    pub fn ring(self, radius: usize) -> Vec<Axial> {
+        if radius == 0 {
+            return vec![self];
+        }
+
+        let mut hex = self + NEIGHBOURS[4] * radius as isize;
+        let mut ring = Vec::with_capacity(6 * radius);
+        for direction in NEIGHBOURS.iter() {
+            for _ in 0..radius {
+                ring.push(hex);
+                hex = hex + *direction;
+            }
+        }
+        ring
+    }
+
+    /// Every hex within `radius` steps, starting in the middle and working outwards a ring at
+    /// a time.
+    pub fn spiral(self, radius: usize) -> Vec<Axial> {
+        (0..=radius).flat_map(|r| self.ring(r)).collect()
+    }
+
+    /// The hexes a straight line from the centre of this hex to the centre of `other` passes
+    /// through, including both ends.
+    pub fn line_to(self, other: Axial) -> Vec<Axial> {
+        let n = self.distance(other);
+        // Nudging the ends stops the line from running exactly along hex edges, where rounding
+        // would pick sides inconsistently.
+        let (a, b) = (self.to_cube(), other.to_cube());
+        let start = (a.q as f64 + 1e-6, a.r as f64 + 1e-6, a.s as f64 - 2e-6);
+        let end = (b.q as f64 + 1e-6, b.r as f64 + 1e-6, b.s as f64 - 2e-6);
+
+        (0..=n)
+            .map(|i| {
+                let t = if n == 0 { 0.0 } else { i as f64 / n as f64 };
+                Cube::round(
+                    start.0 + (end.0 - start.0) * t,
+                    start.1 + (end.1 - start.1) * t,
+                    start.2 + (end.2 - start.2) * t,
+                )
+                .to_axial()
+            })
+            .collect()
+    }
+
+    pub fn to_offset(self, layout: OffsetLayout) -> Offset {
+        let (q, r) = (self.q, self.r);
+        match layout {
+            OffsetLayout::OddR => Offset::new(q + (r - (r & 1)) / 2, r),
+            OffsetLayout::EvenR => Offset::new(q + (r + (r & 1)) / 2, r),
+            OffsetLayout::OddQ => Offset::new(q, r + (q - (q & 1)) / 2),
+            OffsetLayout::EvenQ => Offset::new(q, r + (q + (q & 1)) / 2),
+        }
+    }
+}
+
+impl Add for Axial {
+    type Output = Axial;
+
+    fn add(self, other: Axial) -> Axial {
+        Axial::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Axial {
+    type Output = Axial;
+
+    fn sub(self, other: Axial) -> Axial {
+        Axial::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Mul<isize> for Axial {
+    type Output = Axial;
+
+    fn mul(self, k: isize) -> Axial {
+        Axial::new(self.q * k, self.r * k)
+    }
+}
+
+impl Cube {
+    pub fn to_axial(self) -> Axial {
+        Axial::new(self.q, self.r)
+    }
+
+    /// The hex containing the fractional cube position `(q, r, s)`.
+    pub fn round(q: f64, r: f64, s: f64) -> Cube {
+        let (mut rq, mut rr, mut rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+        // Rounding each axis on its own can break q + r + s == 0, so recompute whichever one
+        // moved furthest from the other two.
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        } else {
+            rs = -rq - rr;
+        }
+
+        Cube {
+            q: rq as isize,
+            r: rr as isize,
+            s: rs as isize,
+        }
+    }
+}
+
+impl From<Axial> for Cube {
+    fn from(a: Axial) -> Cube {
+        a.to_cube()
+    }
+}
+
+impl From<Cube> for Axial {
+    fn from(c: Cube) -> Axial {
+        c.to_axial()
+    }
+}
+
+/// The usual ways of storing a hex grid in a rectangular array. `R` layouts are for pointy
+/// tops and shove every other row sideways, `Q` layouts are for flat tops and shove every
+/// other column down. `Odd` and `Even` say which rows or columns are shoved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OffsetLayout {
+    OddR,
+    EvenR,
+    OddQ,
+    EvenQ,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Offset {
+    pub col: isize,
+    pub row: isize,
+}
+
+impl Offset {
+    pub fn new(col: isize, row: isize) -> Self {
+        Offset { col, row }
+    }
+
+    pub fn to_axial(self, layout: OffsetLayout) -> Axial {
+        let (col, row) = (self.col, self.row);
+        match layout {
+            OffsetLayout::OddR => Axial::new(col - (row - (row & 1)) / 2, row),
+            OffsetLayout::EvenR => Axial::new(col - (row + (row & 1)) / 2, row),
+            OffsetLayout::OddQ => Axial::new(col, row - (col - (col & 1)) / 2),
+            OffsetLayout::EvenQ => Axial::new(col, row - (col + (col & 1)) / 2),
+        }
+    }
+}
+
+/// Compass directions. Pointy-topped hexes have neighbours east and west but not north and
+/// south, and flat-topped hexes the other way round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unexpected direction at position {}", self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Direction {
+    pub fn delta(self, top: Top) -> Option<Axial> {
+        let (q, r) = match (top, self) {
+            (Top::Pointy, Direction::East) => (1, 0),
+            (Top::Pointy, Direction::NorthEast) => (1, -1),
+            (Top::Pointy, Direction::NorthWest) => (0, -1),
+            (Top::Pointy, Direction::West) => (-1, 0),
+            (Top::Pointy, Direction::SouthWest) => (-1, 1),
+            (Top::Pointy, Direction::SouthEast) => (0, 1),
+            (Top::Flat, Direction::North) => (0, -1),
+            (Top::Flat, Direction::NorthEast) => (1, -1),
+            (Top::Flat, Direction::SouthEast) => (1, 0),
+            (Top::Flat, Direction::South) => (0, 1),
+            (Top::Flat, Direction::SouthWest) => (-1, 1),
+            (Top::Flat, Direction::NorthWest) => (-1, 0),
+            _ => return None,
+        };
+        Some(Axial::new(q, r))
+    }
+
+    /// Reads directions written together with no separators, like `esenee`, using
+    /// `n`, `ne`, `e`, `se`, `s`, `sw`, `w` and `nw`. Only the directions that exist for `top`
+    /// are accepted.
+    pub fn parse_path(input: &str, top: Top) -> Result<Vec<Direction>, ParseError> {
+        let bytes = input.as_bytes();
+        let mut res = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let next = bytes.get(i + 1).copied();
+            let (direction, len) = match (bytes[i], next) {
+                (b'n', Some(b'e')) => (Direction::NorthEast, 2),
+                (b'n', Some(b'w')) => (Direction::NorthWest, 2),
+                (b's', Some(b'e')) => (Direction::SouthEast, 2),
+                (b's', Some(b'w')) => (Direction::SouthWest, 2),
+                (b'n', _) => (Direction::North, 1),
+                (b's', _) => (Direction::South, 1),
+                (b'e', _) => (Direction::East, 1),
+                (b'w', _) => (Direction::West, 1),
+                _ => return Err(ParseError { position: i }),
+            };
+
+            if direction.delta(top).is_none() {
+                return Err(ParseError { position: i });
+            }
+            res.push(direction);
+            i += len;
+        }
+
+        Ok(res)
+    }
+
+    /// Where following `directions` from the origin ends up.
+    pub fn resolve(directions: &[Direction], top: Top) -> Axial {
+        directions
+            .iter()
+            .filter_map(|d| d.delta(top))
+            .fold(Axial::default(), |acc, d| acc + d)
+    }
+}
+
+/// Draws `hexes` as text, one character per hex, using `glyph` to choose each character.
+/// Pointy-topped rows are shifted half a hex from the row above, and flat-topped columns
+/// half a hex from the column beside them, with spaces filling the gaps.
+pub fn render<F: Fn(Axial) -> char>(hexes: &HashSet<Axial>, top: Top, glyph: F) -> String {
+    // Doubled coordinates, where every hex lands on a whole number of characters.
+    let place = |a: &Axial| match top {
+        Top::Pointy => (2 * a.q + a.r, a.r),
+        Top::Flat => (a.q, 2 * a.r + a.q),
+    };
+
+    let places: Vec<_> = hexes.iter().map(place).collect();
+    let (minx, maxx) = match (places.iter().map(|p| p.0).min(), places.iter().map(|p| p.0).max()) {
+        (Some(min), Some(max)) => (min, max),
+        _ => return String::new(),
+    };
+    let miny = places.iter().map(|p| p.1).min().unwrap();
+    let maxy = places.iter().map(|p| p.1).max().unwrap();
+
+    let mut res = String::new();
+    for y in miny..=maxy {
+        let mut line = String::new();
+        for x in minx..=maxx {
+            let hex = match top {
+                Top::Pointy if (x - y).rem_euclid(2) == 0 => Some(Axial::new((x - y) / 2, y)),
+                Top::Flat if (y - x).rem_euclid(2) == 0 => Some(Axial::new(x, (y - x) / 2)),
+                _ => None,
+            };
+            match hex {
+                Some(hex) if hexes.contains(&hex) => line.push(glyph(hex)),
+                _ => line.push(' '),
+            }
+        }
+        res.push_str(line.trim_end());
+        res.push('\n');
+    }
+    res
+}
@@ -0,0 +1,153 @@
+use hex_grid::*;
+use std::collections::HashSet;
+
+const DIRECTIONS: [Direction; 8] = [
+    Direction::North,
+    Direction::NorthEast,
+    Direction::East,
+    Direction::SouthEast,
+    Direction::South,
+    Direction::SouthWest,
+    Direction::West,
+    Direction::NorthWest,
+];
+
+#[test]
+fn deltas_test() {
+    for top in &[Top::Pointy, Top::Flat] {
+        let deltas: HashSet<_> = DIRECTIONS.iter().filter_map(|d| d.delta(*top)).collect();
+        let neighbours: HashSet<_> = NEIGHBOURS.iter().copied().collect();
+        assert_eq!(neighbours, deltas);
+    }
+
+    let origin = Axial::default();
+    assert_eq!(None, origin.step(Direction::North, Top::Pointy));
+    assert_eq!(Some(Axial::new(0, -1)), origin.step(Direction::North, Top::Flat));
+    assert_eq!(Some(Axial::new(1, 0)), origin.step(Direction::East, Top::Pointy));
+    assert_eq!(None, origin.step(Direction::East, Top::Flat));
+}
+
+#[test]
+fn parse_path_test() {
+    assert_eq!(
+        Ok(vec![
+            Direction::NorthWest,
+            Direction::West,
+            Direction::SouthWest,
+            Direction::East,
+            Direction::East
+        ]),
+        Direction::parse_path("nwwswee", Top::Pointy)
+    );
+    assert_eq!(
+        Ok(vec![Direction::North, Direction::SouthEast]),
+        Direction::parse_path("nse", Top::Flat)
+    );
+
+    assert_eq!(Err(ParseError { position: 0 }), Direction::parse_path("ns", Top::Pointy));
+    assert_eq!(Err(ParseError { position: 0 }), Direction::parse_path("e", Top::Flat));
+    assert_eq!(Err(ParseError { position: 1 }), Direction::parse_path("ex", Top::Pointy));
+}
+
+#[test]
+fn resolve_test() {
+    let path = Direction::parse_path("nwwswee", Top::Pointy).unwrap();
+    assert_eq!(Axial::new(0, 0), Direction::resolve(&path, Top::Pointy));
+
+    let path = Direction::parse_path("esew", Top::Pointy).unwrap();
+    assert_eq!(Axial::new(0, 1), Direction::resolve(&path, Top::Pointy));
+}
+
+#[test]
+fn distance_test() {
+    assert_eq!(0, Axial::new(2, -3).distance(Axial::new(2, -3)));
+    assert_eq!(3, Axial::new(0, 0).distance(Axial::new(3, -1)));
+    assert_eq!(4, Axial::new(2, -3).distance(Axial::new(-1, 1)));
+}
+
+#[test]
+fn ring_test() {
+    let centre = Axial::new(1, 2);
+    assert_eq!(vec![centre], centre.ring(0));
+
+    assert_eq!(
+        vec![
+            Axial::new(-1, 1),
+            Axial::new(0, 1),
+            Axial::new(1, 0),
+            Axial::new(1, -1),
+            Axial::new(0, -1),
+            Axial::new(-1, 0),
+        ],
+        Axial::default().ring(1)
+    );
+
+    let ring = centre.ring(3);
+    assert_eq!(18, ring.len());
+    assert_eq!(18, ring.iter().collect::<HashSet<_>>().len());
+    assert!(ring.iter().all(|h| h.distance(centre) == 3));
+}
+
+#[test]
+fn spiral_test() {
+    let centre = Axial::new(-2, 0);
+    let spiral = centre.spiral(2);
+    assert_eq!(19, spiral.len());
+    assert_eq!(centre, spiral[0]);
+    assert_eq!(19, spiral.iter().collect::<HashSet<_>>().len());
+    assert!(spiral.iter().all(|h| h.distance(centre) <= 2));
+}
+
+#[test]
+fn line_test() {
+    assert_eq!(vec![Axial::new(1, 1)], Axial::new(1, 1).line_to(Axial::new(1, 1)));
+    assert_eq!(
+        vec![Axial::new(0, 0), Axial::new(1, 0), Axial::new(2, 0), Axial::new(3, 0)],
+        Axial::new(0, 0).line_to(Axial::new(3, 0))
+    );
+
+    let (from, to) = (Axial::new(-3, 1), Axial::new(4, -6));
+    let line = from.line_to(to);
+    assert_eq!(from.distance(to) + 1, line.len());
+    assert_eq!(from, line[0]);
+    assert_eq!(to, line[line.len() - 1]);
+    assert!(line.windows(2).all(|w| w[0].distance(w[1]) == 1));
+}
+
+#[test]
+fn cube_test() {
+    let a = Axial::new(3, -1);
+    assert_eq!(Cube { q: 3, r: -1, s: -2 }, Cube::from(a));
+    assert_eq!(a, Axial::from(a.to_cube()));
+
+    assert_eq!(Cube { q: 1, r: 0, s: -1 }, Cube::round(0.4, 0.3, -0.7));
+}
+
+#[test]
+fn offset_test() {
+    assert_eq!(Offset::new(-1, 1), Axial::new(-1, 1).to_offset(OffsetLayout::OddR));
+    assert_eq!(Offset::new(0, 1), Axial::new(-1, 1).to_offset(OffsetLayout::EvenR));
+    assert_eq!(Offset::new(1, -1), Axial::new(1, -1).to_offset(OffsetLayout::OddQ));
+    assert_eq!(Offset::new(1, 0), Axial::new(1, -1).to_offset(OffsetLayout::EvenQ));
+
+    for layout in &[
+        OffsetLayout::OddR,
+        OffsetLayout::EvenR,
+        OffsetLayout::OddQ,
+        OffsetLayout::EvenQ,
+    ] {
+        for hex in Axial::new(1, -1).spiral(4) {
+            assert_eq!(hex, hex.to_offset(*layout).to_axial(*layout));
+        }
+    }
+}
+
+#[test]
+fn render_test() {
+    let hexes: HashSet<_> = Axial::default().spiral(1).into_iter().collect();
+    let glyph = |h: Axial| if h == Axial::default() { '#' } else { '.' };
+
+    assert_eq!(" . .\n. # .\n . .\n", render(&hexes, Top::Pointy, glyph));
+    assert_eq!(" .\n. .\n #\n. .\n .\n", render(&hexes, Top::Flat, glyph));
+    assert_eq!("", render(&HashSet::new(), Top::Flat, glyph));
+}
@@ -1,33 +1,56 @@
-fn main() {
-    let card_public_key = 6270530;
-    let door_public_key = 14540258;
-
-    let mut loop_number = 1;
-    loop {
-        let res = transform(7, loop_number);
-        if  res == card_public_key {
-            println!("Encryption key is {}", transform(door_public_key, loop_number));
-            break;
-        } else if res == door_public_key {
-            println!("Encryption key is {}", transform(card_public_key, loop_number));
-            break;
-        }
-
-        loop_number += 1;
-        if loop_number % 1000 == 0 {
-            println!("Trying {}", loop_number);
-        }
+use number_theory::{discrete_log, mod_pow};
+
+pub const MODULUS: i128 = 20201227;
+pub const SUBJECT: i128 = 7;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<_> = std::env::args().collect();
+    let option = |name: &str, default: i128| match args.iter().position(|a| a == name) {
+        Some(i) => args[i + 1].parse().expect("options should be numbers"),
+        None => default,
+    };
+
+    let input = std::fs::read_to_string(&args[1])?;
+    let keys = input
+        .lines()
+        .map(|l| l.trim().parse())
+        .collect::<Result<Vec<i128>, _>>()?;
+    if keys.len() != 2 {
+        return Err("expected the card's and the door's public keys".into());
     }
-}
 
-pub fn transform(subject: usize, loop_number: usize) -> usize {
-    let mut value = 1;
-    for _ in 0..loop_number {
-        value *= subject;
-        value = value % 20201227;
+    let modulus = option("--modulus", MODULUS);
+    let subject = option("--subject", SUBJECT);
+    if !(2..1 << 63).contains(&modulus) {
+        return Err("the modulus has to be between 2 and 2^63".into());
     }
+    let key = encryption_key(keys[0], keys[1], subject, modulus)
+        .ok_or_else(|| format!("no loop size turns {} into {}", subject, keys[0]))?;
+    println!("Encryption key is {}", key);
+
+    Ok(())
+}
+
+pub fn transform(subject: i128, loop_number: i128, modulus: i128) -> i128 {
+    mod_pow(subject, loop_number, modulus)
+}
+
+/// The smallest loop size that transforms `subject` into `public_key`. `modulus` has to be
+/// prime and below 2^63, so that products of two values fit in an `i128`, and this is quick
+/// as long as `modulus - 1` has no more than one large prime factor.
+pub fn loop_size(subject: i128, public_key: i128, modulus: i128) -> Option<i128> {
+    discrete_log(subject, public_key, modulus, modulus - 1)
+}
 
-    value
+/// The key the card and door agree on, found by working out the card's loop size.
+pub fn encryption_key(
+    card_key: i128,
+    door_key: i128,
+    subject: i128,
+    modulus: i128,
+) -> Option<i128> {
+    loop_size(subject, card_key, modulus)
+        .map(|loop_number| transform(door_key, loop_number, modulus))
 }
 
 #[cfg(test)]
@@ -38,14 +61,20 @@ mod tests_part1 {
     fn test_card() {
         let card_public_key = 5764801;
         let card_loop_number = 8;
-        assert_eq!(card_public_key, transform(7, card_loop_number));
+        assert_eq!(
+            card_public_key,
+            transform(SUBJECT, card_loop_number, MODULUS)
+        );
     }
 
     #[test]
     fn test_door() {
         let door_public_key = 17807724;
         let door_loop_number = 11;
-        assert_eq!(door_public_key, transform(7, door_loop_number));
+        assert_eq!(
+            door_public_key,
+            transform(SUBJECT, door_loop_number, MODULUS)
+        );
     }
 
     #[test]
@@ -54,9 +83,40 @@ mod tests_part1 {
         let card_loop_number = 8;
         let door_public_key = 17807724;
         let door_loop_number = 11;
-        let card_key = transform(door_public_key, card_loop_number);
-        let door_key = transform(card_public_key, door_loop_number);
+        let card_key = transform(door_public_key, card_loop_number, MODULUS);
+        let door_key = transform(card_public_key, door_loop_number, MODULUS);
         assert_eq!(door_key, card_key);
         assert_eq!(14897079, door_key);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_loop_sizes() {
+        assert_eq!(Some(8), loop_size(SUBJECT, 5764801, MODULUS));
+        assert_eq!(Some(11), loop_size(SUBJECT, 17807724, MODULUS));
+    }
+
+    #[test]
+    fn test_encryption_key() {
+        assert_eq!(
+            Some(14897079),
+            encryption_key(5764801, 17807724, SUBJECT, MODULUS)
+        );
+        assert_eq!(
+            Some(14897079),
+            encryption_key(17807724, 5764801, SUBJECT, MODULUS)
+        );
+    }
+
+    #[test]
+    fn test_other_modulus() {
+        // 2 generates everything modulo 1000003, but 4 only reaches the squares.
+        let card = transform(2, 123456, 1000003);
+        let door = transform(2, 654321, 1000003);
+        assert_eq!(Some(123456), loop_size(2, card, 1000003));
+        assert_eq!(
+            Some(transform(card, 654321, 1000003)),
+            encryption_key(card, door, 2, 1000003)
+        );
+        assert_eq!(None, loop_size(4, 3, 1000003));
+    }
+}
@@ -1,5 +1,7 @@
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{self, BufRead};
+use std::ops::RangeInclusive;
 
 pub struct Map {
     trees: Vec<Vec<bool>>
@@ -60,4 +62,82 @@ impl Map {
 
         count
     }
+
+    pub fn height(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.trees.first().map_or(0, Vec::len)
+    }
+
+    /// Counts the trees hit by each of `slopes` (given as `(right, down)`) in a single pass
+    /// over the rows, returning the counts in the same order as the slopes.
+    pub fn count_trees_many(&self, slopes: &[(usize, usize)]) -> Vec<usize> {
+        assert!(slopes.iter().all(|&(_, down)| down > 0), "down must be at least 1");
+
+        let mut counts = vec![0; slopes.len()];
+        for row in 0..self.height() {
+            for (count, &(right, down)) in counts.iter_mut().zip(slopes) {
+                if row % down == 0 && self.is_tree(row, row / down * right) {
+                    *count += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    /// Finds the slope within the given ranges that hits the fewest trees, returning it along
+    /// with its tree count. Ties go to the slope that comes first, ordered by right then down.
+    pub fn best_slope(
+        &self,
+        rights: RangeInclusive<usize>,
+        downs: RangeInclusive<usize>,
+    ) -> Option<((usize, usize), usize)> {
+        let slopes: Vec<(usize, usize)> = rights
+            .flat_map(|right| downs.clone().filter(|&d| d > 0).map(move |down| (right, down)))
+            .collect();
+        let counts = self.count_trees_many(&slopes);
+
+        slopes
+            .into_iter()
+            .zip(counts)
+            .min_by_key(|&(_, count)| count)
+    }
+
+    /// The `(row, col)` positions visited by the slope, starting from the top-left. Columns are
+    /// not wrapped, so they are positions on the infinitely repeating map.
+    pub fn path(&self, right: usize, down: usize) -> Vec<(usize, usize)> {
+        assert!(down > 0, "down must be at least 1");
+        (0..self.height())
+            .step_by(down)
+            .map(|row| (row, row / down * right))
+            .collect()
+    }
+
+    /// Draws the map, repeated to the right as far as the path goes, with the path overlaid:
+    /// `O` where it crosses open ground and `X` where it hits a tree.
+    pub fn render_path(&self, right: usize, down: usize) -> String {
+        let path: HashSet<(usize, usize)> = self.path(right, down).into_iter().collect();
+        let last_col = path.iter().map(|&(_, col)| col).max().unwrap_or(0);
+        let width = self.width().max(1);
+        let cols = (last_col / width + 1) * width;
+
+        let mut result = String::new();
+        for row in 0..self.height() {
+            for col in 0..cols {
+                let tree = self.is_tree(row, col);
+                result.push(match (path.contains(&(row, col)), tree) {
+                    (true, true) => 'X',
+                    (true, false) => 'O',
+                    (false, true) => '#',
+                    (false, false) => '.',
+                });
+            }
+            result.push('\n');
+        }
+
+        result
+    }
 }
\ No newline at end of file
@@ -4,12 +4,14 @@ use trees::Map;
 fn main() {
     let args: Vec<String> = env::args().collect();
     let map = Map::parse_file(&args[1]);
-    let mut result = 1;
-    result *= map.count_trees(1, 1);
-    result *= map.count_trees(3, 1);
-    result *= map.count_trees(5, 1);
-    result *= map.count_trees(7, 1);
-    result *= map.count_trees(1, 2);
-
+    let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+    let result: usize = map.count_trees_many(&slopes).iter().product();
     println!("The solution is {}", result);
+
+    if let Some(((right, down), count)) = map.best_slope(1..=map.width(), 1..=3) {
+        println!(
+            "The best slope is right {}, down {}, hitting {} trees",
+            right, down, count
+        );
+    }
 }
@@ -68,4 +68,54 @@ fn assert_slope(expected: usize, right: usize, down: usize) {
         .#..#...#.#");
 
     assert_eq!(expected, map.count_trees(right, down));
-}
\ No newline at end of file
+}
+
+#[test]
+fn count_trees_many_matches_single_slopes() {
+    let map = example();
+    let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+    assert_eq!(vec![2, 7, 3, 4, 2], map.count_trees_many(&slopes));
+}
+
+#[test]
+fn best_slope_test() {
+    let map = example();
+    assert_eq!(Some(((5, 2), 0)), map.best_slope(1..=7, 1..=2));
+    assert_eq!(Some(((2, 1), 1)), map.best_slope(1..=4, 1..=1));
+    assert_eq!(None, map.best_slope(1..=7, 0..=0));
+}
+
+#[test]
+fn path_test() {
+    let map = example();
+    assert_eq!(vec![(0, 0), (2, 1), (4, 2), (6, 3), (8, 4), (10, 5)], map.path(1, 2));
+}
+
+#[test]
+fn render_path_test() {
+    let map = example();
+    let rendered = map.render_path(3, 1);
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    assert_eq!(11, lines.len());
+    assert_eq!("O.##.........##.........##.......", lines[0]);
+    assert_eq!("#..O#...#..#...#...#..#...#...#..", lines[1]);
+    assert_eq!(".#....X..#..#....#..#..#....#..#.", lines[2]);
+    assert_eq!(7, rendered.matches('X').count());
+    assert_eq!(4, rendered.matches('O').count());
+}
+
+fn example() -> Map {
+    Map::parse_string(
+       "..##.......
+        #...#...#..
+        .#....#..#.
+        ..#.#...#.#
+        .#...##..#.
+        ..#.##.....
+        .#.#.#....#
+        .#........#
+        #.##...#...
+        #...##....#
+        .#..#...#.#")
+}
@@ -1,6 +1,11 @@
+mod schema;
+
+use schema::Schema;
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
+use std::sync::OnceLock;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -11,7 +16,7 @@ fn main() {
     let mut passports = Vec::new();
     for line in reader.lines() {
         let l = line.unwrap();
-        if l.len() == 0 {
+        if l.is_empty() {
             let passport = Passport::from_string(&current);
             passports.push(passport);
             current = String::from("");
@@ -22,166 +27,67 @@ fn main() {
     let passport = Passport::from_string(&current);
     passports.push(passport);
 
+    let schema = match args.get(2).filter(|a| *a != "--report") {
+        Some(path) => match Schema::from_file(path) {
+            Ok(schema) => schema,
+            Err(e) => {
+                println!("Could not load schema {}: {}", path, e);
+                return;
+            }
+        },
+        None => Schema::strict(),
+    };
+    let report = args.iter().any(|a| a == "--report");
+    let presence = Schema::presence();
+
     println!("Found {} total passports", passports.len());
     println!(
-        "Found {} valid passports",
-        passports.iter().filter(|p| p.is_valid()).count()
+        "Found {} passports with all required fields",
+        passports
+            .iter()
+            .filter(|p| presence.validate(p).is_valid())
+            .count()
     );
-}
-
-pub struct Passport {
-    birth_year: Option<String>,
-    issue_year: Option<String>,
-    expiration_year: Option<String>,
-    height: Option<String>,
-    hair_color: Option<String>,
-    eye_color: Option<String>,
-    passport_id: Option<String>,
-    _country_id: Option<String>, // Uneeded to solve problem
-}
 
-impl Passport {
-    pub fn from_string(data: &str) -> Passport {
-        let mut birth_year = None;
-        let mut issue_year = None;
-        let mut expiration_year = None;
-        let mut height = None;
-        let mut hair_color = None;
-        let mut eye_color = None;
-        let mut passport_id = None;
-        let mut country_id = None;
-        let parts = data.split_whitespace();
-        for p in parts {
-            let mut items = p.split(':');
-            let name = items.next().unwrap();
-            let value = items.next().unwrap();
-            match name {
-                "byr" => {
-                    birth_year = Some(value.into());
-                }
-                "iyr" => {
-                    issue_year = Some(String::from(value));
-                }
-                "eyr" => {
-                    expiration_year = Some(String::from(value));
-                }
-                "hgt" => {
-                    height = Some(String::from(value));
-                }
-                "hcl" => {
-                    hair_color = Some(String::from(value));
-                }
-                "ecl" => {
-                    eye_color = Some(String::from(value));
-                }
-                "pid" => {
-                    passport_id = Some(String::from(value));
-                }
-                "cid" => {
-                    country_id = Some(String::from(value));
-                }
-
-                &_ => {}
+    let mut valid = 0;
+    for (i, passport) in passports.iter().enumerate() {
+        let result = schema.validate(passport);
+        if result.is_valid() {
+            valid += 1;
+        } else if report {
+            println!("Passport {} is invalid:", i + 1);
+            for failure in &result.failures {
+                println!("    {}", failure);
             }
         }
-        Passport {
-            birth_year: birth_year,
-            issue_year: issue_year,
-            expiration_year: expiration_year,
-            height: height,
-            hair_color: hair_color,
-            eye_color: eye_color,
-            passport_id: passport_id,
-            _country_id: country_id,
-        }
-    }
-
-    pub fn is_valid(&self) -> bool {
-        match &self.birth_year {
-            Some(byr) => is_valid_year(&byr, 1920, 2002) && match &self.issue_year {
-                Some(iyr) => is_valid_year(&iyr, 2010, 2020) && match &self.expiration_year {
-                    Some(eyr) => is_valid_year(&eyr, 2020, 2030) && match &self.height {
-                        Some(h) => is_valid_height(h) && match &self.hair_color {
-                            Some(hcl) => is_valid_hair_color(hcl) && match &self.eye_color {
-                                Some(ecl) => is_valid_eye_color(ecl) && match &self.passport_id {
-                                    Some(pid) => is_valid_passport_id(pid),
-                                    None => false,
-                                },
-                                None => false,
-                            },
-                            None => false,
-                        },
-                        None => false,
-                    },
-                    None => false,
-                },
-                None => false,
-            },
-            None => false,
-        }
     }
+    println!("Found {} valid passports", valid);
 }
 
-fn is_valid_year(value: &str, min: usize, max: usize) -> bool {
-    if value.len() != 4 {
-        return false;
-    }
-
-    let v = value.parse::<usize>().unwrap();
-    min <= v && v <= max
+/// The `key:value` fields of a single passport, in no particular order.
+pub struct Passport {
+    fields: HashMap<String, String>,
 }
 
-fn is_valid_hair_color(value: &str) -> bool {
-    if value.len() != 7 {
-        return false;
-    }
-
-    let mut chars = value.chars();
-    if chars.next().unwrap() != '#' {
-        return false;
-    }
-
-    for _ in 1..6 {
-        if !chars.next().unwrap().is_digit(16) {
-            return false;
+impl Passport {
+    pub fn from_string(data: &str) -> Passport {
+        let mut fields = HashMap::new();
+        for p in data.split_whitespace() {
+            let (name, value) = p.split_once(':').unwrap_or((p, ""));
+            fields.insert(name.to_string(), value.to_string());
         }
-    }
-    true
-}
-
-fn is_valid_eye_color(value: &str) -> bool {
-    match value {
-        "amb" => true,
-        "blu" => true,
-        "brn" => true,
-        "gry" => true,
-        "grn" => true,
-        "hzl" => true,
-        "oth" => true,
-        _ => false,
-    }
-}
 
-fn is_valid_passport_id(value: &str) -> bool {
-    value.len() == 9 && value.chars().all(|c| c.is_digit(10))
-}
-
-fn is_valid_height(value: &str) -> bool {
-    if value.ends_with(&"cm") {
-        return is_between(value, 150, 193);
-    } else if value.ends_with(&"in") {
-        return is_between(value, 59, 76);
+        Passport { fields }
     }
 
-    false
-}
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields.get(field).map(String::as_str)
+    }
 
-fn is_between(value: &str, min: usize, max: usize) -> bool {
-    let len = value.len();
-    let v = value[0..len - 2].parse::<usize>();
-    match v {
-        Ok(vv) => vv >= min && vv <= max,
-        Err(_) => false,
+    /// Checks against the puzzle's strict schema, which is only built the first time.
+    pub fn is_valid(&self) -> bool {
+        static STRICT: OnceLock<Schema> = OnceLock::new();
+        STRICT.get_or_init(Schema::strict).validate(self).is_valid()
     }
 }
 
@@ -227,6 +133,67 @@ fn is_valid_passport_fourth() {
     assert!(!passport.is_valid());
 }
 
+#[test]
+fn report_names_every_failing_field() {
+    let passport = Passport::from_string(
+        "eyr:1972 cid:100
+        hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+    );
+
+    let failures: Vec<String> = Schema::strict()
+        .validate(&passport)
+        .failures
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "eyr: '1972' fails year=2020-2030",
+            "hgt: '170' fails height=cm:150-193,in:59-76",
+            "pid: '186cm' fails digits=9",
+        ],
+        failures
+    );
+}
+
+#[test]
+fn report_names_missing_fields() {
+    let passport = Passport::from_string("hcl:#cfa07d eyr:2025 pid:166559648 iyr:2011 ecl:brn");
+
+    let report = Schema::presence().validate(&passport);
+    let missing: Vec<&str> = report.failures.iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(vec!["byr", "hgt"], missing);
+}
+
+#[test]
+fn custom_schema() {
+    let schema = Schema::parse(
+        "# Only the hair colour matters
+        hcl: required hex-color one-of=#123abc",
+    )
+    .unwrap();
+
+    assert!(schema.validate(&Passport::from_string("hcl:#123abc")).is_valid());
+    assert!(!schema.validate(&Passport::from_string("hcl:#123abz")).is_valid());
+    assert_eq!(2, schema.validate(&Passport::from_string("hcl:123abc")).failures.len());
+    assert!(matches!(
+        Schema::parse("hcl: hex-colour"),
+        Err(schema::Error::InvalidRule { line: 1, .. })
+    ));
+}
+
+#[test]
+fn year_rejects_non_numeric_values() {
+    assert!(!schema::Rule::Year { min: 1920, max: 2002 }.check("19x0"));
+}
+
+#[cfg(test)]
+fn is_valid_height(value: &str) -> bool {
+    schema::Rule::parse("height=cm:150-193,in:59-76")
+        .unwrap()
+        .check(value)
+}
+
 #[test]
 fn test_height_60in() {
     assert!(is_valid_height("60in"));
@@ -0,0 +1,257 @@
+use crate::Passport;
+use std::fmt;
+use std::fs;
+
+/// The fields that part 1 of the puzzle requires, with no checks on their values.
+pub const PRESENCE: &str = "byr: required
+iyr: required
+eyr: required
+hgt: required
+hcl: required
+ecl: required
+pid: required
+cid:";
+
+/// The full set of rules from part 2 of the puzzle.
+pub const STRICT: &str = "byr: required year=1920-2002
+iyr: required year=2010-2020
+eyr: required year=2020-2030
+hgt: required height=cm:150-193,in:59-76
+hcl: required hex-color
+ecl: required one-of=amb,blu,brn,gry,grn,hzl,oth
+pid: required digits=9
+cid:";
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidRule { line: usize, rule: String },
+    MissingFieldName { line: usize },
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidRule { line, rule } => {
+                write!(f, "invalid rule '{}' on line {}", rule, line)
+            }
+            Error::MissingFieldName { line } => write!(f, "missing field name on line {}", line),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unit {
+    pub suffix: String,
+    pub min: u32,
+    pub max: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    Required,
+    Year { min: u32, max: u32 },
+    Height(Vec<Unit>),
+    HexColor,
+    OneOf(Vec<String>),
+    Digits(usize),
+}
+
+impl Rule {
+    pub fn parse(text: &str) -> Option<Rule> {
+        let (name, args) = match text.split_once('=') {
+            Some((name, args)) => (name, Some(args)),
+            None => (text, None),
+        };
+
+        match (name, args) {
+            ("required", None) => Some(Rule::Required),
+            ("year", Some(range)) => {
+                let (min, max) = parse_range(range)?;
+                Some(Rule::Year { min, max })
+            }
+            ("height", Some(units)) => {
+                let mut result = Vec::new();
+                for unit in units.split(',') {
+                    let (suffix, range) = unit.split_once(':')?;
+                    let (min, max) = parse_range(range)?;
+                    result.push(Unit {
+                        suffix: suffix.to_string(),
+                        min,
+                        max,
+                    });
+                }
+                Some(Rule::Height(result))
+            }
+            ("hex-color", None) => Some(Rule::HexColor),
+            ("one-of", Some(values)) => {
+                Some(Rule::OneOf(values.split(',').map(String::from).collect()))
+            }
+            ("digits", Some(count)) => count.parse().ok().map(Rule::Digits),
+            _ => None,
+        }
+    }
+
+    /// Checks a value that is present. `Required` is satisfied by any present value.
+    pub fn check(&self, value: &str) -> bool {
+        match self {
+            Rule::Required => true,
+            Rule::Year { min, max } => value.len() == 4 && in_range(value, *min, *max),
+            Rule::Height(units) => units.iter().any(|u| {
+                value
+                    .strip_suffix(u.suffix.as_str())
+                    .is_some_and(|v| in_range(v, u.min, u.max))
+            }),
+            Rule::HexColor => match value.strip_prefix('#') {
+                Some(hex) => hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+                None => false,
+            },
+            Rule::OneOf(values) => values.iter().any(|v| v == value),
+            Rule::Digits(count) => {
+                value.len() == *count && value.chars().all(|c| c.is_ascii_digit())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::Required => write!(f, "required"),
+            Rule::Year { min, max } => write!(f, "year={}-{}", min, max),
+            Rule::Height(units) => {
+                let units: Vec<String> = units
+                    .iter()
+                    .map(|u| format!("{}:{}-{}", u.suffix, u.min, u.max))
+                    .collect();
+                write!(f, "height={}", units.join(","))
+            }
+            Rule::HexColor => write!(f, "hex-color"),
+            Rule::OneOf(values) => write!(f, "one-of={}", values.join(",")),
+            Rule::Digits(count) => write!(f, "digits={}", count),
+        }
+    }
+}
+
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    let (min, max) = range.split_once('-')?;
+    Some((min.parse().ok()?, max.parse().ok()?))
+}
+
+fn in_range(value: &str, min: u32, max: u32) -> bool {
+    value.chars().all(|c| c.is_ascii_digit())
+        && value.parse::<u32>().is_ok_and(|v| min <= v && v <= max)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    pub field: String,
+    pub rule: Rule,
+    pub value: Option<String>,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}: '{}' fails {}", self.field, value, self.rule),
+            None => write!(f, "{}: missing, but {}", self.field, self.rule),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A set of fields, each with the rules its value has to satisfy. Schema files have one
+/// field per line, written as `name: rule rule ...`, and lines starting with `#` are
+/// ignored. See `STRICT` for an example.
+#[derive(Debug)]
+pub struct Schema {
+    fields: Vec<(String, Vec<Rule>)>,
+}
+
+impl Schema {
+    pub fn parse(text: &str) -> Result<Schema, Error> {
+        let mut fields = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, rules) = line.split_once(':').unwrap_or((line, ""));
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(Error::MissingFieldName { line: index + 1 });
+            }
+
+            let mut parsed = Vec::new();
+            for rule in rules.split_whitespace() {
+                match Rule::parse(rule) {
+                    Some(r) => parsed.push(r),
+                    None => {
+                        return Err(Error::InvalidRule {
+                            line: index + 1,
+                            rule: rule.to_string(),
+                        })
+                    }
+                }
+            }
+            fields.push((name.to_string(), parsed));
+        }
+
+        Ok(Schema { fields })
+    }
+
+    pub fn from_file(path: &str) -> Result<Schema, Error> {
+        Schema::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn presence() -> Schema {
+        Schema::parse(PRESENCE).unwrap()
+    }
+
+    pub fn strict() -> Schema {
+        Schema::parse(STRICT).unwrap()
+    }
+
+    /// Checks every rule of every field, so the report names all of the problems rather than
+    /// just the first one.
+    pub fn validate(&self, passport: &Passport) -> Report {
+        let mut report = Report::default();
+        for (field, rules) in &self.fields {
+            let value = passport.get(field);
+            for rule in rules {
+                let ok = match value {
+                    Some(v) => rule.check(v),
+                    None => *rule != Rule::Required,
+                };
+
+                if !ok {
+                    report.failures.push(Failure {
+                        field: field.clone(),
+                        rule: rule.clone(),
+                        value: value.map(String::from),
+                    });
+                }
+            }
+        }
+
+        report
+    }
+}
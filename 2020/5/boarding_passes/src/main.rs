@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
@@ -12,24 +11,19 @@ fn main() -> Result<(), Error> {
     //     println!("The largest id is {}", max);
     // }
 
-    let mut all_passes = HashSet::new();
+    let codec = Codec::standard();
+    let mut seat_map = SeatMap::new(&codec);
     for line in reader.lines() {
-        if let Ok(l) = line {
-            let bp = BoardingPass::new(&l)?;
-            all_passes.insert(bp.seat_id());
-        }
+        let bp = BoardingPass::with_codec(&line?, &codec)?;
+        seat_map.occupy(bp.seat_id());
     }
 
-    for row in 1..127 {
-        for col in 0..7 {
-            let id = 8 * row + col;
-            if !all_passes.contains(&id)
-                && all_passes.contains(&(id - 1))
-                && all_passes.contains(&(id + 1))
-            {
-                println!("Your seat id is {}", id);
-            }
-        }
+    if args.iter().any(|a| a == "--map") {
+        print!("{}", seat_map);
+    }
+
+    for id in seat_map.gaps() {
+        println!("Your seat id is {} ({})", id, codec.encode(id)?);
     }
 
     Ok(())
@@ -38,12 +32,10 @@ fn main() -> Result<(), Error> {
 fn _max_id<R: BufRead>(reader: R) -> Result<usize, Error> {
     let mut max = 0;
     for line in reader.lines() {
-        if let Ok(l) = line {
-            let bp = BoardingPass::new(&l)?;
-            let id = bp.seat_id();
-            if id > max {
-                max = id
-            }
+        let bp = BoardingPass::new(&line?)?;
+        let id = bp.seat_id();
+        if id > max {
+            max = id
         }
     }
 
@@ -51,12 +43,16 @@ fn _max_id<R: BufRead>(reader: R) -> Result<usize, Error> {
 }
 
 pub struct BoardingPass {
-    definition: String,
+    row: usize,
+    col: usize,
+    col_bits: usize,
 }
 
 #[derive(Debug)]
 pub enum Error {
-    InvalidBoardingPass,
+    InvalidLength { expected: usize, found: usize },
+    InvalidCharacter { position: usize, found: char },
+    SeatOutOfRange(usize),
     IOError(std::io::Error),
 }
 
@@ -68,67 +64,180 @@ impl std::convert::From<std::io::Error> for Error {
 
 impl BoardingPass {
     pub fn new(definition: &str) -> Result<BoardingPass, Error> {
-        if definition.len() == 10
-            && chars_in(&definition[0..7], &['F', 'B'])
-            && chars_in(&definition[8..10], &['L', 'R'])
-        {
-            Ok(BoardingPass {
-                definition: definition.into(),
-            })
-        } else {
-            Err(Error::InvalidBoardingPass)
-        }
+        BoardingPass::with_codec(definition, &Codec::standard())
+    }
+
+    pub fn with_codec(definition: &str, codec: &Codec) -> Result<BoardingPass, Error> {
+        let (row, col) = codec.decode(definition)?;
+        Ok(BoardingPass {
+            row,
+            col,
+            col_bits: codec.col_bits,
+        })
     }
 
     pub fn row(&self) -> usize {
-        bsp(&self.definition[0..7], 'B', 'F')
+        self.row
     }
 
     pub fn col(&self) -> usize {
-        bsp(&self.definition[7..10], 'R', 'L')
+        self.col
     }
 
     pub fn seat_id(&self) -> usize {
-        8 * self.row() + self.col()
+        (self.row << self.col_bits) | self.col
     }
 }
 
-fn chars_in(val: &str, allowed: &[char]) -> bool {
-    for c in val.chars() {
-        if !allowed.contains(&c) {
-            return false;
+/// Describes how a plane's seats are written on boarding passes: `row_bits` letters pick
+/// the row and are followed by `col_bits` letters that pick the column. Each pair of
+/// letters is `(lower half, upper half)`, so the letters are really the bits of the seat
+/// id, most significant first.
+pub struct Codec {
+    row_bits: usize,
+    col_bits: usize,
+    row_letters: (char, char),
+    col_letters: (char, char),
+}
+
+impl Codec {
+    pub fn new(
+        row_bits: usize,
+        col_bits: usize,
+        row_letters: (char, char),
+        col_letters: (char, char),
+    ) -> Codec {
+        Codec {
+            row_bits,
+            col_bits,
+            row_letters,
+            col_letters,
+        }
+    }
+
+    /// The 128 row, 8 column plane from the puzzle.
+    pub fn standard() -> Codec {
+        Codec::new(7, 3, ('F', 'B'), ('L', 'R'))
+    }
+
+    pub fn rows(&self) -> usize {
+        1 << self.row_bits
+    }
+
+    pub fn cols(&self) -> usize {
+        1 << self.col_bits
+    }
+
+    pub fn seats(&self) -> usize {
+        self.rows() * self.cols()
+    }
+
+    /// Decodes a boarding pass into its `(row, col)`.
+    pub fn decode(&self, pass: &str) -> Result<(usize, usize), Error> {
+        let len = pass.chars().count();
+        if len != self.row_bits + self.col_bits {
+            return Err(Error::InvalidLength {
+                expected: self.row_bits + self.col_bits,
+                found: len,
+            });
+        }
+
+        let mut row = 0;
+        let mut col = 0;
+        for (position, c) in pass.chars().enumerate() {
+            let (letters, value) = if position < self.row_bits {
+                (self.row_letters, &mut row)
+            } else {
+                (self.col_letters, &mut col)
+            };
+
+            *value <<= 1;
+            if c == letters.1 {
+                *value |= 1;
+            } else if c != letters.0 {
+                return Err(Error::InvalidCharacter { position, found: c });
+            }
+        }
+
+        Ok((row, col))
+    }
+
+    pub fn encode(&self, seat_id: usize) -> Result<String, Error> {
+        if seat_id >= self.seats() {
+            return Err(Error::SeatOutOfRange(seat_id));
+        }
+
+        let row = seat_id >> self.col_bits;
+        let col = seat_id & (self.cols() - 1);
+        let mut pass = String::new();
+        for bit in (0..self.row_bits).rev() {
+            pass.push(letter(row, bit, self.row_letters));
+        }
+        for bit in (0..self.col_bits).rev() {
+            pass.push(letter(col, bit, self.col_letters));
         }
+
+        Ok(pass)
     }
+}
+
+fn letter(value: usize, bit: usize, letters: (char, char)) -> char {
+    if value & (1 << bit) == 0 {
+        letters.0
+    } else {
+        letters.1
+    }
+}
 
-    true
+/// Which seats on a plane are taken, laid out by row and column.
+pub struct SeatMap {
+    cols: usize,
+    occupied: Vec<bool>,
 }
 
-fn bsp(val: &str, up: char, down: char) -> usize {
-    let mut min = 0;
-    let mut max = (2 << val.len() - 1) - 1;
-    let mut mid = max / 2;
-
-    //println!("Initial: min: {}, mid: {}, max: {}", min, mid, max);
-    for c in val.chars() {
-        if c == up {
-            min = mid + 1;
-        } else if c == down {
-            max = mid;
-        } else {
-            panic!();
+impl SeatMap {
+    pub fn new(codec: &Codec) -> SeatMap {
+        SeatMap {
+            cols: codec.cols(),
+            occupied: vec![false; codec.seats()],
         }
+    }
+
+    pub fn occupy(&mut self, seat_id: usize) {
+        self.occupied[seat_id] = true;
+    }
 
-        mid = (min + max) / 2;
+    pub fn is_occupied(&self, seat_id: usize) -> bool {
+        self.occupied[seat_id]
+    }
 
-        //println!("After {} - min: {}, mid: {}, max: {}", c, min, mid, max);
+    pub fn free_seats(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.occupied.len()).filter(move |&id| !self.occupied[id])
     }
 
-    mid
+    /// Free seats that have taken seats on both sides, which is where the puzzle says our
+    /// seat is, as opposed to the free seats at the very front and back of the plane.
+    pub fn gaps(&self) -> impl Iterator<Item = usize> + '_ {
+        self.free_seats().filter(move |&id| {
+            id > 0 && id + 1 < self.occupied.len() && self.occupied[id - 1] && self.occupied[id + 1]
+        })
+    }
+}
+
+impl std::fmt::Display for SeatMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (row, seats) in self.occupied.chunks(self.cols).enumerate() {
+            let seats: String = seats.iter().map(|&o| if o { '#' } else { '.' }).collect();
+            writeln!(f, "{:>4} {}", row, seats)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{BoardingPass, Error};
+    use super::{BoardingPass, Codec, Error, SeatMap};
 
     #[test]
     fn example1() -> Result<(), Error> {
@@ -165,4 +274,50 @@ mod test {
         assert_eq!(820, boarding_pass.seat_id());
         Ok(())
     }
+
+    #[test]
+    fn round_trip() -> Result<(), Error> {
+        let codec = Codec::standard();
+        for id in 0..codec.seats() {
+            assert_eq!(id, BoardingPass::new(&codec.encode(id)?)?.seat_id());
+        }
+        assert_eq!("FBFBBFFRLR", codec.encode(357)?);
+        Ok(())
+    }
+
+    #[test]
+    fn custom_codec() -> Result<(), Error> {
+        let codec = Codec::new(2, 2, ('a', 'b'), ('0', '1'));
+        assert_eq!((2, 1), codec.decode("ba01")?);
+        assert_eq!("ba01", codec.encode(9)?);
+        assert_eq!(9, BoardingPass::with_codec("ba01", &codec)?.seat_id());
+        assert!(matches!(codec.encode(16), Err(Error::SeatOutOfRange(16))));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_passes() {
+        assert!(matches!(
+            BoardingPass::new("FBFBBFFRL"),
+            Err(Error::InvalidLength { expected: 10, found: 9 })
+        ));
+        assert!(matches!(
+            BoardingPass::new("FBFBBFLRLR"),
+            Err(Error::InvalidCharacter { position: 6, found: 'L' })
+        ));
+    }
+
+    #[test]
+    fn seat_map() {
+        let codec = Codec::new(2, 2, ('F', 'B'), ('L', 'R'));
+        let mut map = SeatMap::new(&codec);
+        for id in [1, 2, 3, 5, 6] {
+            map.occupy(id);
+        }
+
+        assert!(map.is_occupied(5));
+        assert_eq!(vec![0, 4, 7, 8], map.free_seats().take(4).collect::<Vec<_>>());
+        assert_eq!(vec![4], map.gaps().collect::<Vec<_>>());
+        assert_eq!("   0 .###\n   1 .##.\n   2 ....\n   3 ....\n", map.to_string());
+    }
 }
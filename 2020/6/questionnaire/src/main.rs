@@ -1,21 +1,89 @@
-use std::collections::HashSet;
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead};
-
+use std::ops::{BitAnd, BitOr, Sub};
 
 fn main() -> Result<(), io::Error> {
     let args: Vec<String> = env::args().collect();
     let reader = io::BufReader::new(File::open(&args[1])?);
-    let sum = Group::parse(reader)?.iter().fold(0, |acc, x| acc + x.num_all_yes());
+    let groups = Group::parse(reader)?;
+
+    let query = match Query::parse(args.get(2).map_or("all", String::as_str)) {
+        Ok(query) => query,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    let sum = groups.iter().fold(0, |acc, x| acc + query.eval(x).len());
 
     println!("The sum is {}", sum);
 
+    if args.iter().any(|a| a == "--histogram") {
+        for (i, count) in histogram(&groups).iter().enumerate() {
+            println!("{} {:>5}", (b'a' + i as u8) as char, count);
+        }
+    }
+
     Ok(())
 }
 
+/// The set of questions (`a` to `z`) someone answered "yes" to, stored as one bit per question.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Answers(u32);
+
+impl Answers {
+    pub const ALL: Answers = Answers((1 << 26) - 1);
+
+    pub fn parse(line: &str) -> Answers {
+        Answers(
+            line.bytes()
+                .filter(u8::is_ascii_lowercase)
+                .fold(0, |acc, b| acc | 1 << (b - b'a')),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, question: char) -> bool {
+        question.is_ascii_lowercase() && self.0 & 1 << (question as u8 - b'a') != 0
+    }
+
+    pub fn questions(&self) -> impl Iterator<Item = char> + '_ {
+        ('a'..='z').filter(move |&q| self.contains(q))
+    }
+}
+
+impl BitOr for Answers {
+    type Output = Answers;
+    fn bitor(self, rhs: Answers) -> Answers {
+        Answers(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Answers {
+    type Output = Answers;
+    fn bitand(self, rhs: Answers) -> Answers {
+        Answers(self.0 & rhs.0)
+    }
+}
+
+impl Sub for Answers {
+    type Output = Answers;
+    fn sub(self, rhs: Answers) -> Answers {
+        Answers(self.0 & !rhs.0)
+    }
+}
+
 pub struct Group {
-    vals: Vec<String>,
+    people: Vec<Answers>,
 }
 
 impl Group {
@@ -40,25 +108,185 @@ impl Group {
 
     pub fn new<I: Into<Vec<String>>>(vals: I) -> Group {
         Group {
-            vals: vals.into(),
+            people: vals.into().iter().map(|v| Answers::parse(v)).collect(),
         }
     }
 
+    pub fn people(&self) -> &[Answers] {
+        &self.people
+    }
+
+    pub fn any_yes(&self) -> Answers {
+        self.people.iter().fold(Answers::default(), |acc, &p| acc | p)
+    }
+
+    pub fn all_yes(&self) -> Answers {
+        self.people.iter().fold(Answers::ALL, |acc, &p| acc & p)
+    }
+
+    /// The questions that exactly `k` people in the group answered "yes" to.
+    pub fn exactly(&self, k: usize) -> Answers {
+        let counts = self.histogram();
+        Answers(
+            (0..26)
+                .filter(|&q| counts[q] == k)
+                .fold(0, |acc, q| acc | 1 << q),
+        )
+    }
+
+    /// How many people in the group answered "yes" to each question.
+    pub fn histogram(&self) -> [usize; 26] {
+        let mut counts = [0; 26];
+        for person in &self.people {
+            for (q, count) in counts.iter_mut().enumerate() {
+                *count += (person.0 >> q & 1) as usize;
+            }
+        }
+        counts
+    }
+
     pub fn num_any_yes(&self) -> usize {
-        let all_yes : HashSet<_> = self.vals.iter().flat_map(|c| c.chars()).collect();
-        all_yes.len()
+        self.any_yes().len()
     }
 
     pub fn num_all_yes(&self) -> usize {
-        let letters = "abcdefghijklmnopqrstuvwxyz";
-        let mut remaining_set : HashSet<_> = letters.chars().collect();
+        self.all_yes().len()
+    }
+}
+
+/// How many people across all of the groups answered "yes" to each question.
+pub fn histogram(groups: &[Group]) -> [usize; 26] {
+    let mut counts = [0; 26];
+    for group in groups {
+        for (total, count) in counts.iter_mut().zip(group.histogram()) {
+            *total += count;
+        }
+    }
+    counts
+}
+
+/// A set expression evaluated against each group. The terms are `any`, `all` and
+/// `exactly(k)`, combined with `|` (union), `&` (intersection) and `-` (difference),
+/// which all have the same precedence and group left to right. Parentheses can be used to
+/// group differently, e.g. `any - (all | exactly(1))`.
+#[derive(Debug, PartialEq)]
+pub enum Query {
+    Any,
+    All,
+    Exactly(usize),
+    Union(Box<Query>, Box<Query>),
+    Intersection(Box<Query>, Box<Query>),
+    Difference(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct QueryError {
+    pub position: usize,
+    pub message: String,
+}
 
-        for v in &self.vals {
-            let h : HashSet<_> = v.chars().collect();
-            remaining_set.retain(|c| h.contains(c));
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let mut parser = QueryParser { text, position: 0 };
+        let query = parser.expression()?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("unexpected input"));
         }
+        Ok(query)
+    }
+
+    pub fn eval(&self, group: &Group) -> Answers {
+        match self {
+            Query::Any => group.any_yes(),
+            Query::All => group.all_yes(),
+            Query::Exactly(k) => group.exactly(*k),
+            Query::Union(l, r) => l.eval(group) | r.eval(group),
+            Query::Intersection(l, r) => l.eval(group) & r.eval(group),
+            Query::Difference(l, r) => l.eval(group) - r.eval(group),
+        }
+    }
+}
+
+struct QueryParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> QueryParser<'a> {
+    fn error(&self, message: &str) -> QueryError {
+        QueryError {
+            position: self.position,
+            message: message.to_string(),
+        }
+    }
 
-        remaining_set.len()
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.text[self.position..].starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Result<Query, QueryError> {
+        let mut query = self.term()?;
+        loop {
+            let op: fn(Box<Query>, Box<Query>) -> Query = if self.eat("|") {
+                Query::Union
+            } else if self.eat("&") {
+                Query::Intersection
+            } else if self.eat("-") {
+                Query::Difference
+            } else {
+                return Ok(query);
+            };
+            query = op(Box::new(query), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Query, QueryError> {
+        if self.eat("(") {
+            let query = self.expression()?;
+            if !self.eat(")") {
+                return Err(self.error("expected ')'"));
+            }
+            Ok(query)
+        } else if self.eat("any") {
+            Ok(Query::Any)
+        } else if self.eat("all") {
+            Ok(Query::All)
+        } else if self.eat("exactly") {
+            if !self.eat("(") {
+                return Err(self.error("expected '('"));
+            }
+            self.skip_whitespace();
+            let rest = &self.text[self.position..];
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let k = rest[..digits]
+                .parse()
+                .map_err(|_| self.error("expected a number"))?;
+            self.position += digits;
+            if !self.eat(")") {
+                return Err(self.error("expected ')'"));
+            }
+            Ok(Query::Exactly(k))
+        } else {
+            Err(self.error("expected 'any', 'all', 'exactly(k)' or '('"))
+        }
     }
 }
 
@@ -137,3 +365,70 @@ mod tests_part2 {
         assert_eq!(1, group.num_all_yes());
     }
 }
+
+#[cfg(test)]
+mod tests_query {
+    use super::*;
+
+    fn example() -> Vec<Group> {
+        let input = "abc
+
+a
+b
+c
+
+ab
+ac
+
+a
+a
+a
+a
+
+b";
+        Group::parse(input.as_bytes()).unwrap()
+    }
+
+    fn sum(query: &str) -> usize {
+        let query = Query::parse(query).unwrap();
+        example().iter().map(|g| query.eval(g).len()).sum()
+    }
+
+    #[test]
+    fn any_and_all() {
+        assert_eq!(11, sum("any"));
+        assert_eq!(6, sum("all"));
+        assert_eq!(5, sum("any - all"));
+        assert_eq!(11, sum("all | any"));
+        assert_eq!(6, sum("all & any"));
+    }
+
+    #[test]
+    fn exactly() {
+        let group = Group::new(["ab".into(), "ac".into()]);
+        assert_eq!(vec!['b', 'c'], group.exactly(1).questions().collect::<Vec<_>>());
+        assert_eq!(vec!['a'], group.exactly(2).questions().collect::<Vec<_>>());
+        assert_eq!(9, sum("exactly(1)"));
+        assert_eq!(0, sum("any - (all | exactly(1))"));
+        assert_eq!(2, sum("all - exactly( 1 )"));
+    }
+
+    #[test]
+    fn histograms() {
+        let counts = histogram(&example());
+        assert_eq!(&[8, 4, 3], &counts[..3]);
+        assert!(counts[3..].iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err(QueryError {
+                position: 6,
+                message: "expected 'any', 'all', 'exactly(k)' or '('".into()
+            }),
+            Query::parse("any | none")
+        );
+        assert_eq!(4, Query::parse("(any").unwrap_err().position);
+    }
+}
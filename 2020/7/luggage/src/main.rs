@@ -1,142 +1,275 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
+use std::fmt;
 use std::fs;
-use std::hash::{Hash, Hasher};
 
 fn main() -> Result<(), std::io::Error> {
     let args : Vec<_> = env::args().collect();
     let input = fs::read_to_string(&args[1])?;
-    let rules = Rules::parse(&input);
+    let rules = match Rules::parse(&input) {
+        Ok(rules) => rules,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+
+    if args.iter().any(|a| a == "--dot") {
+        print!("{}", rules.to_dot());
+        return Ok(());
+    }
+
+    if let Some(containers) = rules.can_eventually_contain("shiny gold") {
+        println!("shiny gold bags can be inside: {}", containers.len());
+    }
 
-    println!("shiny gold bags contain: {}", rules.contains_recursive("shiny gold"));
+    match rules.contains_recursive("shiny gold") {
+        Ok(count) => println!("shiny gold bags contain: {}", count),
+        Err(e) => println!("{}", e),
+    }
 
     Ok(())
 }
 
-pub struct Rules<'a> {
-    rules: HashSet<Rule<'a>>,
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    InvalidRule { line: usize, text: String },
+    UnknownBag(String),
+    Cycle(Vec<String>),
 }
 
-impl<'a> Rules<'a> {
-    pub fn parse(input: &'a str) -> Rules<'a> {
-        Rules {
-            rules: input
-                .split('\n')
-                .map(|line| Rule::parse(&line))
-                .collect(),
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidRule { line, text } => {
+                write!(f, "line {} is not a valid rule: {}", line, text)
+            }
+            Error::UnknownBag(name) => write!(f, "there is no rule for {} bags", name),
+            Error::Cycle(bags) => write!(f, "bags contain themselves: {}", bags.join(" -> ")),
         }
     }
+}
 
-    pub fn can_eventually_contain(&self, bag_name: &str) -> Option<HashSet<&'a Rule>> {
-        if let Some(r) = self.rules.iter().filter(|x| x.name == bag_name).nth(0) {
-            let mut v = HashSet::new();
-            v.insert(r);
-            v = self.can_contain_recursive(v, 0);
-            v.remove(r);
-            Some(v)
-        } else {
-            None
-        }
-    }
+/// One way that a bag ends up inside another, listing the bags from the outside in, and how
+/// many of the innermost bag that chain accounts for.
+#[derive(Debug, PartialEq)]
+pub struct Path<'a> {
+    pub bags: Vec<&'a str>,
+    pub count: usize,
+}
 
-    fn can_contain_recursive(&self, rules: HashSet<&'a Rule<'a>>, depth: usize) -> HashSet<&'a Rule> {
-        let names : Vec<&'a str> = rules.iter().map(|r| r.name).collect();
-        println!("Looking for rules that can contain {:?}", names);
-        let mut new = HashSet::new();
-        for r1 in &self.rules {
-            for r2 in &rules {
-                if r1.can_contain_directly(r2.name) {
-                    new.insert(r1);
-                }
+/// The rules as a directed graph, with bags numbered by the order they first appear. Edges
+/// point from a bag to the bags it directly contains, and are also kept in reverse so that
+/// "what can hold this bag" doesn't need a scan over every rule.
+pub struct Rules<'a> {
+    names: Vec<&'a str>,
+    index: HashMap<&'a str, usize>,
+    contents: Vec<Vec<(usize, usize)>>,
+    containers: Vec<Vec<usize>>,
+}
+
+impl<'a> Rules<'a> {
+    pub fn parse(input: &'a str) -> Result<Rules<'a>, Error> {
+        let mut rules = Rules {
+            names: Vec::new(),
+            index: HashMap::new(),
+            contents: Vec::new(),
+            containers: Vec::new(),
+        };
+
+        for (line, text) in input.lines().enumerate() {
+            if text.trim().is_empty() {
+                continue;
             }
-        }
 
-        let len = rules.len();
-        for r in rules {
-            new.insert(r);
+            let rule = Rule::parse(text).ok_or_else(|| Error::InvalidRule {
+                line: line + 1,
+                text: text.to_string(),
+            })?;
+            let outer = rules.node(rule.name);
+            for (name, count) in rule.contains {
+                let inner = rules.node(name);
+                rules.contents[outer].push((inner, count));
+                rules.containers[inner].push(outer);
+            }
         }
 
-        if new.len() == len {
-            return new
+        Ok(rules)
+    }
+
+    fn node(&mut self, name: &'a str) -> usize {
+        if let Some(&i) = self.index.get(name) {
+            return i;
         }
 
-        self.can_contain_recursive(new, depth + 1)
+        self.names.push(name);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        self.index.insert(name, self.names.len() - 1);
+        self.names.len() - 1
     }
 
-    pub fn contains_recursive(&self, bag_name: &str) -> usize {
-        self.contains_recursive_helper(bag_name) - 1
+    fn find(&self, bag_name: &str) -> Result<usize, Error> {
+        self.index
+            .get(bag_name)
+            .copied()
+            .ok_or_else(|| Error::UnknownBag(bag_name.to_string()))
     }
 
-    fn contains_recursive_helper(&self, bag_name: &str) -> usize {
-        if let Some(r) = self.rules.iter().filter(|x| x.name == bag_name).nth(0) {
-            1 + r.contains_recursive(self)
-        } else {
-            0
+    /// Every bag that could eventually hold `bag_name`, found by walking the reverse edges.
+    pub fn can_eventually_contain(&self, bag_name: &str) -> Option<HashSet<&'a str>> {
+        let start = self.find(bag_name).ok()?;
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(bag) = queue.pop_front() {
+            for &outer in &self.containers[bag] {
+                if seen.insert(outer) {
+                    queue.push_back(outer);
+                }
+            }
         }
+
+        seen.remove(&start);
+        Some(seen.into_iter().map(|i| self.names[i]).collect())
     }
-}
 
-#[derive(Debug)]
-pub struct Rule<'a> {
-    name: &'a str,
-    contains: HashSet<(&'a str, usize)>,
-}
+    /// How many bags end up inside `bag_name`. Each bag's total is only worked out once, and
+    /// rules where a bag ends up inside itself are reported instead of recursing forever.
+    pub fn contains_recursive(&self, bag_name: &str) -> Result<usize, Error> {
+        let start = self.find(bag_name)?;
+        let mut memo = vec![None; self.names.len()];
+        let mut stack = Vec::new();
+        self.count_inside(start, &mut memo, &mut stack)
+    }
 
-impl<'a> Rule<'a> {
-    pub fn parse(line: &str) -> Rule {
-        let mut spaces = Vec::new();
-        for (pos, c) in line.chars().enumerate() {
-            if c == ' ' {
-                spaces.push(pos);
-            }
+    fn count_inside(
+        &self,
+        bag: usize,
+        memo: &mut Vec<Option<usize>>,
+        stack: &mut Vec<usize>,
+    ) -> Result<usize, Error> {
+        if let Some(count) = memo[bag] {
+            return Ok(count);
         }
-        spaces.push(line.len());
 
-        let mut contains = Vec::new();
-        if &line[spaces[3]..] == " no other bags." {
-        } else {
-            let mut space = 3;
-            while space < spaces.len() - 1 {
-                let bn = &line[spaces[space + 1] + 1..spaces[space + 3]];
-                let c = line[spaces[space] + 1..spaces[space+1]].parse::<usize>().unwrap();
-                contains.push((bn, c));
-                space += 4;
-            }
+        if let Some(pos) = stack.iter().position(|&b| b == bag) {
+            let mut cycle: Vec<String> = stack[pos..]
+                .iter()
+                .map(|&b| self.names[b].to_string())
+                .collect();
+            cycle.push(self.names[bag].to_string());
+            return Err(Error::Cycle(cycle));
         }
 
-        // Couldn't get collect to work below :(
-        let mut hs = HashSet::new();
-        for s in contains {
-            hs.insert(s);
+        stack.push(bag);
+        let mut total = 0;
+        for &(inner, count) in &self.contents[bag] {
+            total += count * (1 + self.count_inside(inner, memo, stack)?);
         }
+        stack.pop();
 
-        Rule {
-            name: &line[0..spaces[1]],
-            contains: hs,
-        }
+        memo[bag] = Some(total);
+        Ok(total)
     }
 
-    pub fn can_contain_directly(&self, bag_name: &str) -> bool {
-        self.contains.iter().any(|(bn, _)| bn == &bag_name)
+    /// Every chain of bags through which `inner` ends up inside `outer`. Chains never visit the
+    /// same bag twice, so this also terminates for cyclic rules.
+    pub fn paths(&self, outer: &str, inner: &str) -> Result<Vec<Path<'a>>, Error> {
+        let from = self.find(outer)?;
+        let to = self.find(inner)?;
+
+        // Only bags that can hold `inner` are worth exploring.
+        let mut useful = vec![false; self.names.len()];
+        useful[to] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(to);
+        while let Some(bag) = queue.pop_front() {
+            for &o in &self.containers[bag] {
+                if !useful[o] {
+                    useful[o] = true;
+                    queue.push_back(o);
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        if from != to && useful[from] {
+            let mut current = vec![from];
+            self.collect_paths(to, 1, &useful, &mut current, &mut paths);
+        }
+        Ok(paths)
     }
 
-    pub fn contains_recursive(&self, rules: &Rules) -> usize {
-        self.contains.iter().fold(0, |acc, (bn, c)| acc + c * rules.contains_recursive_helper(bn))
+    fn collect_paths(
+        &self,
+        to: usize,
+        count: usize,
+        useful: &[bool],
+        current: &mut Vec<usize>,
+        paths: &mut Vec<Path<'a>>,
+    ) {
+        let bag = *current.last().unwrap();
+        for &(next, n) in &self.contents[bag] {
+            if !useful[next] || current.contains(&next) {
+                continue;
+            }
+
+            current.push(next);
+            if next == to {
+                paths.push(Path {
+                    bags: current.iter().map(|&b| self.names[b]).collect(),
+                    count: count * n,
+                });
+            } else {
+                self.collect_paths(to, count * n, useful, current, paths);
+            }
+            current.pop();
+        }
     }
-}
 
-impl<'a> PartialEq for Rule<'a> {
-    fn eq(&self, rhs : &Rule<'a>) -> bool {
-        self.name == rhs.name
+    /// The containment hierarchy in Graphviz DOT format, with edges labelled by bag count.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bags {\n");
+        for (outer, contents) in self.contents.iter().enumerate() {
+            if contents.is_empty() && self.containers[outer].is_empty() {
+                dot += &format!("    \"{}\";\n", self.names[outer]);
+            }
+            for &(inner, count) in contents {
+                dot += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    self.names[outer], self.names[inner], count
+                );
+            }
+        }
+        dot += "}\n";
+        dot
     }
 }
 
-impl<'a> Eq for Rule<'a> {
+#[derive(Debug)]
+pub struct Rule<'a> {
+    name: &'a str,
+    contains: Vec<(&'a str, usize)>,
 }
 
-impl<'a> Hash for Rule<'a> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name.hash(state)
+impl<'a> Rule<'a> {
+    pub fn parse(line: &'a str) -> Option<Rule<'a>> {
+        let (name, rest) = line.split_once(" bags contain ")?;
+        let rest = rest.trim_end().strip_suffix('.')?;
+
+        let mut contains = Vec::new();
+        if rest != "no other bags" {
+            for part in rest.split(", ") {
+                let (count, bag) = part.split_once(' ')?;
+                let bag = bag
+                    .strip_suffix(" bags")
+                    .or_else(|| bag.strip_suffix(" bag"))?;
+                contains.push((bag, count.parse().ok()?));
+            }
+        }
+
+        Some(Rule { name, contains })
     }
 }
 
@@ -155,7 +288,7 @@ shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
 dark olive bags contain 3 faded blue bags, 4 dotted black bags.
 vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
-dotted black bags contain no other bags.");
+dotted black bags contain no other bags.").unwrap();
 
         if let Some(x) = rules.can_eventually_contain("shiny gold") {
             assert_eq!(4, x.len());
@@ -175,13 +308,13 @@ shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
 dark olive bags contain 3 faded blue bags, 4 dotted black bags.
 vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
-dotted black bags contain no other bags.");
+dotted black bags contain no other bags.").unwrap();
 
-        assert_eq!(0, rules.contains_recursive("faded blue"));
-        assert_eq!(0, rules.contains_recursive("dotted black"));
-        assert_eq!(11, rules.contains_recursive("vibrant plum"));
-        assert_eq!(7, rules.contains_recursive("dark olive"));
-        assert_eq!(32, rules.contains_recursive("shiny gold"));
+        assert_eq!(Ok(0), rules.contains_recursive("faded blue"));
+        assert_eq!(Ok(0), rules.contains_recursive("dotted black"));
+        assert_eq!(Ok(11), rules.contains_recursive("vibrant plum"));
+        assert_eq!(Ok(7), rules.contains_recursive("dark olive"));
+        assert_eq!(Ok(32), rules.contains_recursive("shiny gold"));
     }
 
     #[test]
@@ -193,10 +326,57 @@ dark orange bags contain 2 dark yellow bags.
 dark yellow bags contain 2 dark green bags.
 dark green bags contain 2 dark blue bags.
 dark blue bags contain 2 dark violet bags.
-dark violet bags contain no other bags.");
+dark violet bags contain no other bags.").unwrap();
 
-            assert_eq!(126, rules.contains_recursive("shiny gold"));
+            assert_eq!(Ok(126), rules.contains_recursive("shiny gold"));
     }
-}
 
+    #[test]
+    fn cycles() {
+        let rules = Rules::parse(
+            "shiny gold bags contain 2 dark red bags.
+dark red bags contain 1 dark orange bag, 3 faded blue bags.
+dark orange bags contain 2 dark red bags.
+faded blue bags contain no other bags.").unwrap();
+
+        assert_eq!(
+            Err(Error::Cycle(vec!["dark red".into(), "dark orange".into(), "dark red".into()])),
+            rules.contains_recursive("shiny gold")
+        );
+        assert_eq!(
+            Ok(vec![Path { bags: vec!["shiny gold", "dark red", "faded blue"], count: 6 }]),
+            rules.paths("shiny gold", "faded blue")
+        );
+        assert_eq!(3, rules.can_eventually_contain("faded blue").unwrap().len());
+        assert_eq!(Err(Error::UnknownBag("pink".into())), rules.contains_recursive("pink"));
+    }
 
+    #[test]
+    fn paths_and_dot() {
+        let rules = Rules::parse(
+            "light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain no other bags.
+faded blue bags contain no other bags.").unwrap();
+
+        assert_eq!(Ok(vec![
+            Path { bags: vec!["light red", "bright white", "shiny gold"], count: 1 },
+            Path { bags: vec!["light red", "muted yellow", "shiny gold"], count: 4 },
+        ]), rules.paths("light red", "shiny gold"));
+        assert_eq!(Ok(vec![]), rules.paths("shiny gold", "light red"));
+
+        assert_eq!("digraph bags {
+    \"light red\" -> \"bright white\" [label=\"1\"];
+    \"light red\" -> \"muted yellow\" [label=\"2\"];
+    \"bright white\" -> \"shiny gold\" [label=\"1\"];
+    \"muted yellow\" -> \"shiny gold\" [label=\"2\"];
+    \"muted yellow\" -> \"faded blue\" [label=\"9\"];
+}
+", rules.to_dot());
+        assert_eq!(
+            Some(Error::InvalidRule { line: 1, text: "light red bags".into() }),
+            Rules::parse("light red bags").err()
+        );
+    }
+}
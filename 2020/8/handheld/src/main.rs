@@ -1,29 +1,47 @@
-use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::env;
+use std::fmt;
 use std::fs;
 use std::num::ParseIntError;
 
 fn main() -> Result<(), std::io::Error> {
     let args: Vec<String> = env::args().collect();
     let input = fs::read_to_string(&args[1])?;
-    if let Ok(program) = Program::parse(&input) {
-        for x in 0..program.len() {
-            let mut modified_program = program.to_vec();
-            let new_instr = match program[x] {
-                Instruction::Nop(x) => Instruction::Jmp(x),
-                Instruction::Jmp(x) => Instruction::Nop(x),
-                Instruction::Acc(x) => Instruction::Acc(x),
-            };
-            modified_program[x] = new_instr;
-            let (acc, pc) = Emulator::run(&modified_program);
-            if pc == program.len() {
-                println!("Completed the program with acc value {}", acc);
-                break;
-            }
+    let program = match Program::parse(&input) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("Didn't run: {}", e);
+            return Ok(());
+        }
+    };
 
+    let (outcome, trace) = Emulator::run_traced(&program);
+    if args.iter().any(|a| a == "--trace") {
+        for step in &trace {
+            println!("{:>4}: {:<10} acc = {}", step.pc, step.instruction.to_string(), step.acc);
         }
-    } else {
-        println!("Didn't run");
+    }
+    match outcome {
+        Outcome::Terminated { acc } => println!("The program terminated with acc value {}", acc),
+        Outcome::InfiniteLoop { acc, cycle } => println!(
+            "The program loops through {} instructions starting at line {}, acc value {}",
+            cycle.len(),
+            cycle[0] + 1,
+            acc
+        ),
+        Outcome::OutOfBounds { acc, pc } => {
+            println!("The program jumped to {} with acc value {}", pc, acc)
+        }
+    }
+
+    match Emulator::repair(&program) {
+        Some(repair) => println!(
+            "Changing line {} to {} completed the program with acc value {}",
+            repair.pc + 1,
+            repair.replacement,
+            repair.acc
+        ),
+        None => println!("No single change makes the program terminate"),
     }
 
     Ok(())
@@ -33,8 +51,11 @@ pub struct Program {}
 impl Program {
     pub fn parse(lines: &str) -> Result<Vec<Instruction>, Error> {
         let mut program = Vec::new();
-        for line in lines.lines() {
-            let instr = Instruction::parse(line)?;
+        for (line, text) in lines.lines().enumerate() {
+            let instr = Instruction::parse(text).map_err(|kind| Error {
+                line: line + 1,
+                kind,
+            })?;
             program.push(instr);
         }
 
@@ -42,38 +63,168 @@ impl Program {
     }
 }
 
+/// How a run of a program ended. Every variant carries the accumulator's final value.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// The program counter landed exactly one past the last instruction.
+    Terminated { acc: isize },
+    /// An instruction was about to run a second time. `cycle` lists the instructions of the
+    /// loop in the order they run, starting with the repeated one.
+    InfiniteLoop { acc: isize, cycle: Vec<usize> },
+    /// A jump left the program somewhere other than just past the end.
+    OutOfBounds { acc: isize, pc: isize },
+}
+
+/// A single executed instruction, with the accumulator's value after it ran.
+#[derive(Debug, PartialEq)]
+pub struct Step {
+    pub pc: usize,
+    pub instruction: Instruction,
+    pub acc: isize,
+}
+
+/// A one instruction change that makes a program terminate.
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    pub pc: usize,
+    pub replacement: Instruction,
+    pub acc: isize,
+}
+
 pub struct Emulator {}
 
 impl Emulator {
-    pub fn run(program: &[Instruction]) -> (isize, usize) {
-        let mut seen = HashSet::new();
+    pub fn run(program: &[Instruction]) -> Outcome {
+        Emulator::run_traced(program).0
+    }
+
+    pub fn run_traced(program: &[Instruction]) -> (Outcome, Vec<Step>) {
+        let mut seen = vec![false; program.len()];
+        let mut trace: Vec<Step> = Vec::new();
         let mut pc = 0;
         let mut acc = 0;
 
-        while !seen.contains(&pc) && pc < program.len() as isize {
-            seen.insert(pc);
-            let instr = &program[pc as usize];
-            let (a, p) = instr.exec();
+        loop {
+            if pc == program.len() as isize {
+                return (Outcome::Terminated { acc }, trace);
+            }
+            if pc < 0 || pc > program.len() as isize {
+                return (Outcome::OutOfBounds { acc, pc }, trace);
+            }
+
+            let current = pc as usize;
+            if seen[current] {
+                let start = trace.iter().position(|s| s.pc == current).unwrap();
+                let cycle = trace[start..].iter().map(|s| s.pc).collect();
+                return (Outcome::InfiniteLoop { acc, cycle }, trace);
+            }
+            seen[current] = true;
+
+            let instruction = program[current];
+            let (a, p) = instruction.exec();
             acc += a;
             pc += p;
+            trace.push(Step {
+                pc: current,
+                instruction,
+                acc,
+            });
+        }
+    }
+
+    /// Finds the `jmp` or `nop` that needs to be swapped for the program to terminate, in time
+    /// linear in the program's length. Working backwards from the end of the program over the
+    /// control-flow graph finds every instruction that terminates when run unmodified, so
+    /// the fix is the first instruction on the original path whose swapped successor is one
+    /// of those.
+    pub fn repair(program: &[Instruction]) -> Option<Repair> {
+        let len = program.len();
+        let mut predecessors = vec![Vec::new(); len + 1];
+        for (pc, instruction) in program.iter().enumerate() {
+            if let Some(next) = successor(pc, instruction, len) {
+                predecessors[next].push(pc);
+            }
+        }
+
+        let mut terminates = vec![false; len + 1];
+        terminates[len] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(len);
+        while let Some(pc) = queue.pop_front() {
+            for &prev in &predecessors[pc] {
+                if !terminates[prev] {
+                    terminates[prev] = true;
+                    queue.push_back(prev);
+                }
+            }
+        }
+
+        let (_, trace) = Emulator::run_traced(program);
+        for step in trace {
+            let replacement = match step.instruction {
+                Instruction::Nop(x) => Instruction::Jmp(x),
+                Instruction::Jmp(x) => Instruction::Nop(x),
+                Instruction::Acc(_) => continue,
+            };
+
+            if successor(step.pc, &replacement, len).is_some_and(|next| terminates[next]) {
+                let mut patched = program.to_vec();
+                patched[step.pc] = replacement;
+                if let Outcome::Terminated { acc } = Emulator::run(&patched) {
+                    return Some(Repair {
+                        pc: step.pc,
+                        replacement,
+                        acc,
+                    });
+                }
+            }
         }
 
-        (acc, pc as usize)
+        None
+    }
+}
+
+fn successor(pc: usize, instruction: &Instruction, len: usize) -> Option<usize> {
+    let next = pc as isize + instruction.exec().1;
+    if next >= 0 && next <= len as isize {
+        Some(next as usize)
+    } else {
+        None
     }
 }
 
-pub enum Error {
+#[derive(Debug)]
+pub struct Error {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
     Parse(ParseIntError),
-    UnexpectedInstruction,
+    MissingArgument,
+    UnexpectedInstruction(String),
+}
+
+impl From<ParseIntError> for ErrorKind {
+    fn from(pie: ParseIntError) -> ErrorKind {
+        ErrorKind::Parse(pie)
+    }
 }
 
-impl From<ParseIntError> for Error {
-    fn from(pie: ParseIntError) -> Error {
-        Error::Parse(pie)
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Parse(e) => write!(f, "line {}: {}", self.line, e),
+            ErrorKind::MissingArgument => write!(f, "line {}: missing argument", self.line),
+            ErrorKind::UnexpectedInstruction(op) => {
+                write!(f, "line {}: unexpected instruction '{}'", self.line, op)
+            }
+        }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Instruction {
     Acc(isize),
     Jmp(isize),
@@ -81,15 +232,16 @@ pub enum Instruction {
 }
 
 impl Instruction {
-    pub fn parse(input: &str) -> Result<Instruction, Error> {
+    pub fn parse(input: &str) -> Result<Instruction, ErrorKind> {
         let parts: Vec<&str> = input.split_whitespace().collect();
-        let val = parts[1].parse::<isize>()?;
+        let op = parts.first().copied().unwrap_or("");
+        let val = parts.get(1).ok_or(ErrorKind::MissingArgument)?.parse::<isize>()?;
 
-        match parts[0] {
+        match op {
             "nop" => Ok(Instruction::Nop(val)),
             "acc" => Ok(Instruction::Acc(val)),
             "jmp" => Ok(Instruction::Jmp(val)),
-            _ => Err(Error::UnexpectedInstruction),
+            _ => Err(ErrorKind::UnexpectedInstruction(op.to_string())),
         }
     }
 
@@ -102,6 +254,16 @@ impl Instruction {
     }
 }
 
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Acc(x) => write!(f, "acc {:+}", x),
+            Instruction::Jmp(x) => write!(f, "jmp {:+}", x),
+            Instruction::Nop(x) => write!(f, "nop {:+}", x),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,8 +281,47 @@ jmp -4
 acc +6";
 
         if let Ok(program) = Program::parse(input) {
-            let (acc, _) = Emulator::run(&program);
-            assert_eq!(5, acc);
+            let outcome = Emulator::run(&program);
+            assert_eq!(Outcome::InfiniteLoop { acc: 5, cycle: vec![1, 2, 6, 7, 3, 4] }, outcome);
         }
     }
+
+    #[test]
+    pub fn repair() {
+        let input = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+        let program = Program::parse(input).unwrap();
+        assert_eq!(
+            Some(Repair { pc: 7, replacement: Instruction::Nop(-4), acc: 8 }),
+            Emulator::repair(&program)
+        );
+    }
+
+    #[test]
+    pub fn outcomes_and_trace() {
+        let program = Program::parse("acc +2\njmp +2\nacc +5\nacc -1").unwrap();
+        let (outcome, trace) = Emulator::run_traced(&program);
+        assert_eq!(Outcome::Terminated { acc: 1 }, outcome);
+        assert_eq!(vec![0, 1, 3], trace.iter().map(|s| s.pc).collect::<Vec<_>>());
+        assert_eq!(vec![2, 2, 1], trace.iter().map(|s| s.acc).collect::<Vec<_>>());
+
+        let program = Program::parse("acc +2\njmp -4").unwrap();
+        assert_eq!(Outcome::OutOfBounds { acc: 2, pc: -3 }, Emulator::run(&program));
+    }
+
+    #[test]
+    pub fn errors_have_line_numbers() {
+        let error = Program::parse("nop +0\nacc +1\nhcf +0").unwrap_err();
+        assert_eq!("line 3: unexpected instruction 'hcf'", error.to_string());
+        let error = Program::parse("nop +0\njmp").unwrap_err();
+        assert_eq!("line 2: missing argument", error.to_string());
+    }
 }
@@ -1,66 +1,195 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
+use std::io::{self, BufRead, BufReader};
+use std::num::ParseIntError;
 
 fn main() -> Result<(), Error> {
     let args: Vec<String> = env::args().collect();
     let reader = BufReader::new(File::open(&args[1])?);
+    let window = match args.get(2) {
+        Some(w) => w.parse()?,
+        None => 25,
+    };
 
-    let input : Vec<_> = reader.lines().map(|line| line.unwrap().parse::<usize>().unwrap()).collect();
-    if let Some(weakness) = encryption_weakness(&input, 25) {
-        println!("Found encryption weakness: {}", weakness);
-    } else {
-        println!("Didn't find an encryption weakness.");
+    let scan = Scan::read(reader, window)?;
+    for &(index, num) in &scan.invalid {
+        println!("Line {} ({}) is not the sum of two of the previous {}", index + 1, num, window);
+    }
+
+    match scan.invalid.first().and_then(|&(_, num)| find_weakness(&scan.numbers, num)) {
+        Some(weakness) => println!("Found encryption weakness: {}", weakness),
+        None => println!("Didn't find an encryption weakness."),
     }
 
     Ok(())
 }
 
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse { line: usize, error: ParseIntError },
+    Argument(ParseIntError),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(e: ParseIntError) -> Error {
+        Error::Argument(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse { line, error } => write!(f, "line {}: {}", line, error),
+            Error::Argument(e) => write!(f, "invalid window size: {}", e),
+        }
+    }
+}
+
 pub fn is_last_valid(num: usize, input: &[usize]) -> bool {
-    for x in 0..input.len() - 1 {
-        for y in x + 1..input.len() {
-            //if num == 863 {
-            //    println!("Checking {} + {} = {},", input[x], input[y], input[x] + input[y]);
-            //}
-            if input[x] + input[y] == num && input[x] != input[y] {
-                return true;
+    let values: HashSet<usize> = input.iter().copied().collect();
+    input
+        .iter()
+        .any(|&x| x < num && num - x != x && values.contains(&(num - x)))
+}
+
+/// Checks numbers one at a time against the `window` numbers before them. The sums of every
+/// pair of distinct values in the window are kept in a counted index that is updated as
+/// numbers enter and leave it, so each check is a single lookup rather than a scan of all of
+/// the pairs.
+pub struct Validator {
+    window: usize,
+    recent: VecDeque<usize>,
+    sums: HashMap<usize, usize>,
+}
+
+impl Validator {
+    pub fn new(window: usize) -> Validator {
+        Validator {
+            window,
+            recent: VecDeque::with_capacity(window + 1),
+            sums: HashMap::new(),
+        }
+    }
+
+    /// Adds the next number, returning whether it is valid. The preamble is always valid.
+    pub fn push(&mut self, num: usize) -> bool {
+        let valid = self.recent.len() < self.window || self.sums.contains_key(&num);
+
+        if self.recent.len() == self.window {
+            if let Some(oldest) = self.recent.pop_front() {
+                for &other in &self.recent {
+                    if other != oldest {
+                        let sum = oldest + other;
+                        let count = self.sums.get_mut(&sum).unwrap();
+                        *count -= 1;
+                        if *count == 0 {
+                            self.sums.remove(&sum);
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.window > 0 {
+            for &other in &self.recent {
+                if other != num {
+                    *self.sums.entry(num + other).or_insert(0) += 1;
+                }
             }
+            self.recent.push_back(num);
         }
+
+        valid
     }
+}
 
-    false
+/// The numbers read from a stream, along with the index and value of every invalid one.
+pub struct Scan {
+    pub numbers: Vec<usize>,
+    pub invalid: Vec<(usize, usize)>,
 }
 
-pub fn first_invalid(input: &[usize], preamble_length: usize) -> Option<(usize, usize)> {
-    // Consider - storing a set of previous sums, along with the indices of their addends would allow
-    // fewer iterations if the input was super long, or the preamble length was longer.
-    for x in preamble_length..input.len() {
-        //println!("looking at index {} from {} to {}", x, x-preamble_length, x);
-        if !is_last_valid(input[x], &input[x-preamble_length..x]) {
-            return Some((x,input[x]));
+impl Scan {
+    pub fn read<R: BufRead>(reader: R, window: usize) -> Result<Scan, Error> {
+        let mut validator = Validator::new(window);
+        let mut scan = Scan {
+            numbers: Vec::new(),
+            invalid: Vec::new(),
+        };
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let num = line.trim().parse::<usize>().map_err(|error| Error::Parse {
+                line: index + 1,
+                error,
+            })?;
+
+            if !validator.push(num) {
+                scan.invalid.push((index, num));
+            }
+            scan.numbers.push(num);
         }
+
+        Ok(scan)
     }
+}
 
-    None
+pub fn all_invalid(input: &[usize], preamble_length: usize) -> Vec<(usize, usize)> {
+    let mut validator = Validator::new(preamble_length);
+    input
+        .iter()
+        .enumerate()
+        .filter(|&(_, &num)| !validator.push(num))
+        .map(|(x, &num)| (x, num))
+        .collect()
 }
 
-pub fn encryption_weakness(input: &[usize], preamble_size: usize) -> Option<usize> {
-    let (index, first_invalid) = first_invalid(input, preamble_size)?;
-
-    // Let's just brute force this - how many subsets can there be?
-    for x in 0..index-1 {
-        for y in x+1..index {
-            let val = input[x..y].iter().fold(0, |acc, v| acc+v);
-            if val == first_invalid {
-                let min = input[x..y].iter().min()?;
-                let max = input[x..y].iter().max()?;
-                return Some(min + max);
-            }
+pub fn first_invalid(input: &[usize], preamble_length: usize) -> Option<(usize, usize)> {
+    let mut validator = Validator::new(preamble_length);
+    input
+        .iter()
+        .enumerate()
+        .find(|&(_, &num)| !validator.push(num))
+        .map(|(x, &num)| (x, num))
+}
+
+/// Finds a contiguous run of at least two numbers adding up to `target`, and returns the sum
+/// of its smallest and largest numbers. The run's sum only grows as its end moves right and
+/// only shrinks as its start does, so a single pass with two pointers finds it.
+pub fn find_weakness(input: &[usize], target: usize) -> Option<usize> {
+    let mut start = 0;
+    let mut sum = 0;
+    for end in 0..input.len() {
+        sum += input[end];
+        while sum > target && start < end {
+            sum -= input[start];
+            start += 1;
+        }
+
+        if sum == target && end > start {
+            let range = &input[start..=end];
+            return Some(range.iter().min()? + range.iter().max()?);
         }
     }
+
     None
 }
 
+pub fn encryption_weakness(input: &[usize], preamble_size: usize) -> Option<usize> {
+    let (_, first_invalid) = first_invalid(input, preamble_size)?;
+    find_weakness(input, first_invalid)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,4 +231,33 @@ mod tests {
         let input = [35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309, 576,];
         assert_eq!(Some(62), encryption_weakness(&input, 5));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn every_invalid_position() {
+        let input = [1, 2, 3, 5, 100, 105, 8, 200, 305];
+        assert_eq!(vec![(4, 100), (6, 8), (7, 200)], all_invalid(&input, 3));
+        assert_eq!(Some((4, 100)), first_invalid(&input, 3));
+    }
+
+    #[test]
+    fn repeated_values_in_window() {
+        // 10 + 10 doesn't count, but the second 10 leaving shouldn't drop 10 + 5.
+        let mut validator = Validator::new(3);
+        for n in [10, 10, 5] {
+            assert!(validator.push(n));
+        }
+        assert!(!validator.push(20));
+        assert!(validator.push(15));
+        assert!(validator.push(25));
+    }
+
+    #[test]
+    fn scan_from_reader() {
+        let input = "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576";
+        let scan = Scan::read(input.as_bytes(), 5).unwrap();
+        assert_eq!(20, scan.numbers.len());
+        assert_eq!((14, 127), scan.invalid[0]);
+        assert_eq!(Some(62), find_weakness(&scan.numbers, 127));
+        assert!(matches!(Scan::read("1\nx".as_bytes(), 5), Err(Error::Parse { line: 2, .. })));
+    }
+}
@@ -1,19 +1,83 @@
-fn main() -> Result<(), std::io::Error> {
+use std::io::BufRead;
+
+mod window;
+
+use window::{increases, Windows};
+
+#[derive(Debug)]
+#[allow(dead_code)]
+enum Error {
+    IO(std::io::Error),
+    Parse(std::num::ParseIntError),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(ioe: std::io::Error) -> Self {
+        Error::IO(ioe)
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(pie: std::num::ParseIntError) -> Self {
+        Error::Parse(pie)
+    }
+}
+
+// Usage: count_increases [FILE|-] [--window N] [--stats]
+// Reads standard input when there's no file, or it's "-".
+fn main() -> Result<(), Error> {
     let args: Vec<_> = std::env::args().collect();
-    let input = std::fs::read_to_string(&args[1])?;
-
-    let mut count = 0;
-
-    let lines: Vec<_> = input.lines().map(|x| x.parse::<i32>().unwrap()).collect();
-    let mut last = lines[0] + lines[1] + lines[2];
-    for i in 3..lines.len() {
-        let cur = lines[i] + lines[i-1] + lines[i-2];
-        if cur > last {
-            count += 1;
-        }
-        last = cur
+    let option = |name: &str| args.iter().position(|a| a == name).map(|i| &args[i + 1]);
+    let size = match option("--window") {
+        Some(s) => s.parse()?,
+        None => 3,
+    };
+    let stats = args.iter().any(|a| a == "--stats");
+
+    // The file is the first argument that's neither a flag nor the value of --window.
+    let file = args
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(i, a)| !a.starts_with("--") && args[i - 1] != "--window")
+        .map(|(_, a)| a);
+    let reader: Box<dyn BufRead> = match file.map(String::as_str) {
+        None | Some("-") => Box::new(std::io::stdin().lock()),
+        Some(path) => Box::new(std::io::BufReader::new(std::fs::File::open(path)?)),
+    };
+
+    // Values are read as they're needed, so stop at the first bad line and report it after.
+    let mut error = None;
+    let count = {
+        let values = reader
+            .lines()
+            .filter(|l| !matches!(l, Ok(l) if l.trim().is_empty()))
+            .map(parse)
+            .map_while(|v| v.map_err(|e| error = Some(e)).ok());
+
+        let windows = values.windowed(size).inspect(|w| {
+            if stats {
+                println!(
+                    "{}: sum {}, min {}, max {}, mean {:.2}",
+                    w.end,
+                    w.sum,
+                    w.min,
+                    w.max,
+                    w.mean()
+                );
+            }
+        });
+        increases(windows.map(|w| w.sum))
+    };
+
+    if let Some(e) = error {
+        return Err(e);
     }
 
     println!("There were {} increases", count);
     Ok(())
 }
+
+fn parse(line: std::io::Result<String>) -> Result<i64, Error> {
+    Ok(line?.trim().parse()?)
+}
@@ -0,0 +1,226 @@
+use num_traits::{Num, ToPrimitive, Zero};
+use std::collections::VecDeque;
+
+/// Statistics for one full window of a stream.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Window<T> {
+    /// The position in the stream of the window's last value.
+    pub end: usize,
+    pub size: usize,
+    pub sum: T,
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: ToPrimitive> Window<T> {
+    pub fn mean(&self) -> f64 {
+        self.sum.to_f64().unwrap_or(f64::NAN) / self.size as f64
+    }
+}
+
+/// An iterator adapter giving a [`Window`] for every run of `size` consecutive values, once
+/// there have been enough values to fill one. Each value is handled in constant amortized
+/// time however large the window is: the sum is kept rolling, and the minimum and maximum
+/// come from the front of monotonic deques.
+pub struct Windowed<I: Iterator> {
+    inner: I,
+    size: usize,
+    index: usize,
+    values: VecDeque<I::Item>,
+    sum: I::Item,
+    // Positions and values that could still be the minimum (or maximum) of some window,
+    // increasing (or decreasing) from front to back.
+    mins: VecDeque<(usize, I::Item)>,
+    maxs: VecDeque<(usize, I::Item)>,
+}
+
+pub trait Windows: Iterator + Sized {
+    fn windowed(self, size: usize) -> Windowed<Self>;
+}
+
+impl<I> Windows for I
+where
+    I: Iterator,
+    I::Item: Num + Copy + PartialOrd,
+{
+    fn windowed(self, size: usize) -> Windowed<Self> {
+        assert!(size > 0, "windows must hold at least one value");
+        Windowed {
+            inner: self,
+            size,
+            index: 0,
+            values: VecDeque::with_capacity(size + 1),
+            sum: I::Item::zero(),
+            mins: VecDeque::new(),
+            maxs: VecDeque::new(),
+        }
+    }
+}
+
+impl<I> Iterator for Windowed<I>
+where
+    I: Iterator,
+    I::Item: Num + Copy + PartialOrd,
+{
+    type Item = Window<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let value = self.inner.next()?;
+            let index = self.index;
+            self.index += 1;
+
+            self.values.push_back(value);
+            self.sum = self.sum + value;
+            if self.values.len() > self.size {
+                let old = self.values.pop_front().unwrap();
+                self.sum = self.sum - old;
+            }
+
+            while matches!(self.mins.back(), Some(&(_, v)) if v >= value) {
+                self.mins.pop_back();
+            }
+            self.mins.push_back((index, value));
+            while matches!(self.maxs.back(), Some(&(_, v)) if v <= value) {
+                self.maxs.pop_back();
+            }
+            self.maxs.push_back((index, value));
+
+            if index + 1 < self.size {
+                continue;
+            }
+
+            let start = index + 1 - self.size;
+            while self.mins.front().unwrap().0 < start {
+                self.mins.pop_front();
+            }
+            while self.maxs.front().unwrap().0 < start {
+                self.maxs.pop_front();
+            }
+
+            return Some(Window {
+                end: index,
+                size: self.size,
+                sum: self.sum,
+                min: self.mins.front().unwrap().1,
+                max: self.maxs.front().unwrap().1,
+            });
+        }
+    }
+}
+
+/// How many values are larger than the one before.
+pub fn increases<T: PartialOrd, I: IntoIterator<Item = T>>(values: I) -> usize {
+    let mut values = values.into_iter();
+    let mut previous = match values.next() {
+        Some(v) => v,
+        None => return 0,
+    };
+
+    let mut count = 0;
+    for v in values {
+        if v > previous {
+            count += 1;
+        }
+        previous = v;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [i64; 10] = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+
+    fn extremes(values: &[i64], size: usize) -> Vec<(i64, i64)> {
+        values
+            .iter()
+            .copied()
+            .windowed(size)
+            .map(|w| (w.min, w.max))
+            .collect()
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(7, increases(EXAMPLE));
+        assert_eq!(
+            5,
+            increases(EXAMPLE.iter().copied().windowed(3).map(|w| w.sum))
+        );
+
+        let sums: Vec<_> = EXAMPLE.iter().copied().windowed(3).map(|w| w.sum).collect();
+        assert_eq!(vec![607, 618, 618, 617, 647, 716, 769, 792], sums);
+    }
+
+    #[test]
+    fn single() {
+        let windows: Vec<_> = [4, -1, 7].iter().copied().windowed(1).collect();
+        assert_eq!(
+            vec![
+                Window {
+                    end: 0,
+                    size: 1,
+                    sum: 4,
+                    min: 4,
+                    max: 4
+                },
+                Window {
+                    end: 1,
+                    size: 1,
+                    sum: -1,
+                    min: -1,
+                    max: -1
+                },
+                Window {
+                    end: 2,
+                    size: 1,
+                    sum: 7,
+                    min: 7,
+                    max: 7
+                },
+            ],
+            windows
+        );
+    }
+
+    #[test]
+    fn too_short() {
+        assert_eq!(0, [1, 2].iter().copied().windowed(3).count());
+        assert_eq!(0, std::iter::empty::<i64>().windowed(1).count());
+        assert_eq!(0, increases(Vec::<i64>::new()));
+    }
+
+    #[test]
+    fn eviction() {
+        // The maximum leaves after the first window, and the minimum after the second.
+        assert_eq!(vec![(1, 5), (1, 3), (2, 4)], extremes(&[5, 1, 2, 3, 4], 3));
+        assert_eq!(vec![(1, 9), (2, 9), (2, 8)], extremes(&[1, 9, 2, 8, 3], 3));
+    }
+
+    #[test]
+    fn ties() {
+        assert_eq!(
+            vec![(2, 2), (2, 2), (1, 2), (1, 1)],
+            extremes(&[2, 2, 2, 1, 1], 2)
+        );
+        assert_eq!(vec![(1, 3), (1, 3), (1, 3)], extremes(&[3, 1, 3, 1], 2));
+        assert_eq!(vec![(3, 3), (3, 3)], extremes(&[3, 3, 3, 3], 3));
+    }
+
+    #[test]
+    fn mean() {
+        let means: Vec<_> = [1.0, 2.0, 4.5]
+            .iter()
+            .copied()
+            .windowed(2)
+            .map(|w| w.mean())
+            .collect();
+        assert_eq!(vec![1.5, 3.25], means);
+        assert_eq!(
+            4.0,
+            [3, 4, 5].iter().copied().windowed(3).next().unwrap().mean()
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::Error;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Something the submarine can be told to do. The first three are the puzzle's commands,
+/// whose meaning depends on the interpreter; `Script` runs steps defined in a config file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Forward(i64),
+    Down(i64),
+    Up(i64),
+    Script(Rc<[Step]>, i64),
+}
+
+impl Command {
+    fn build(name: &str, amount: i64) -> Option<Command> {
+        match name {
+            "forward" => Some(Command::Forward(amount)),
+            "down" => Some(Command::Down(amount)),
+            "up" => Some(Command::Up(amount)),
+            _ => None,
+        }
+    }
+}
+
+/// The numbers every interpreter keeps, which script steps read and write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    Horizontal,
+    Depth,
+    Aim,
+}
+
+impl Register {
+    fn parse(name: &str) -> Option<Register> {
+        match name {
+            "horizontal" => Some(Register::Horizontal),
+            "depth" => Some(Register::Depth),
+            "aim" => Some(Register::Aim),
+            _ => None,
+        }
+    }
+}
+
+/// One factor of a product: a constant, the command's amount (`x`), or a register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Factor {
+    Constant(i64),
+    Amount,
+    Register(Register),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    Add(Register, Vec<Factor>),
+    Subtract(Register, Vec<Factor>),
+    Multiply(Register, Vec<Factor>),
+    Set(Register, Vec<Factor>),
+    Swap(Register, Register),
+}
+
+impl Step {
+    /// Reads `<register> <op> <product>` where op is one of `+=`, `-=`, `*=` and `=` and the
+    /// product is factors joined by `*`, or `swap <register> <register>`.
+    fn parse(text: &str) -> Option<Step> {
+        let text = text.trim();
+        if let Some(rest) = text.strip_prefix("swap ") {
+            let mut registers = rest.split_whitespace().map(Register::parse);
+            return match (registers.next(), registers.next(), registers.next()) {
+                (Some(Some(a)), Some(Some(b)), None) => Some(Step::Swap(a, b)),
+                _ => None,
+            };
+        }
+
+        let (target, product) = text.split_once('=')?;
+        let factors = product
+            .split('*')
+            .map(|f| match f.trim() {
+                "x" => Some(Factor::Amount),
+                f => Register::parse(f)
+                    .map(Factor::Register)
+                    .or_else(|| f.parse().ok().map(Factor::Constant)),
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let target = target.trim_end();
+        let (register, op): (_, fn(Register, Vec<Factor>) -> Step) =
+            match target.char_indices().last()? {
+                (i, '+') => (&target[..i], Step::Add),
+                (i, '-') => (&target[..i], Step::Subtract),
+                (i, '*') => (&target[..i], Step::Multiply),
+                _ => (target, Step::Set),
+            };
+        Some(op(Register::parse(register.trim())?, factors))
+    }
+}
+
+/// The words a course can use. By default these are just `forward`, `down` and `up`, and
+/// [`CommandSet::configure`] can add more or redefine them.
+pub struct CommandSet {
+    words: HashMap<String, Definition>,
+}
+
+enum Definition {
+    /// One of the puzzle's commands, with the amount multiplied by a factor.
+    Alias(String, i64),
+    Script(Rc<[Step]>),
+}
+
+impl Default for CommandSet {
+    fn default() -> Self {
+        let words = ["forward", "down", "up"]
+            .iter()
+            .map(|w| (w.to_string(), Definition::Alias(w.to_string(), 1)))
+            .collect();
+        CommandSet { words }
+    }
+}
+
+impl CommandSet {
+    /// Adds words defined one per line, in one of two forms. `back forward -1` is an alias,
+    /// making `back 3` mean `forward -3` to whichever interpreter is running. `turn: aim *= -1`
+    /// is a script of steps separated by `;`, acting directly on the horizontal position,
+    /// depth and aim, with `x` standing for the amount; see [`Step::parse`]. Scripts run the
+    /// same way under every interpreter. Blank lines and lines starting with `#` are skipped.
+    pub fn configure(&mut self, config: &str) -> Result<(), Error> {
+        for (i, line) in config.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (word, definition) = match line.split_once(':') {
+                Some((word, script)) => (word.trim(), Self::script(script, line_number)?),
+                None => Self::alias(line, line_number)?,
+            };
+            if word.is_empty() || word.contains(char::is_whitespace) {
+                return Err(Error::Malformed { line: line_number });
+            }
+
+            self.words.insert(word.to_string(), definition);
+        }
+
+        Ok(())
+    }
+
+    fn alias(line: &str, line_number: usize) -> Result<(&str, Definition), Error> {
+        let mut parts = line.split_whitespace();
+        let (word, primitive, factor) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(w), Some(p), Some(f), None) => (w, p, f),
+                _ => return Err(Error::Malformed { line: line_number }),
+            };
+        if Command::build(primitive, 0).is_none() {
+            return Err(Error::UnknownCommand {
+                line: line_number,
+                word: primitive.to_string(),
+            });
+        }
+        let factor = factor.parse().map_err(|_| Error::InvalidAmount {
+            line: line_number,
+            amount: factor.to_string(),
+        })?;
+
+        Ok((word, Definition::Alias(primitive.to_string(), factor)))
+    }
+
+    fn script(script: &str, line_number: usize) -> Result<Definition, Error> {
+        let steps = script
+            .split(';')
+            .map(|s| {
+                Step::parse(s).ok_or_else(|| Error::InvalidStep {
+                    line: line_number,
+                    step: s.trim().to_string(),
+                })
+            })
+            .collect::<Result<Rc<[Step]>, Error>>()?;
+        Ok(Definition::Script(steps))
+    }
+
+    /// Reads one `<word> <amount>` line of a course. `line_number` is only used for errors.
+    pub fn parse(&self, line: &str, line_number: usize) -> Result<Command, Error> {
+        let mut parts = line.split_whitespace();
+        let (word, amount) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(w), Some(a), None) => (w, a),
+            _ => return Err(Error::Malformed { line: line_number }),
+        };
+
+        let definition = self.words.get(word).ok_or_else(|| Error::UnknownCommand {
+            line: line_number,
+            word: word.to_string(),
+        })?;
+        let invalid = || Error::InvalidAmount {
+            line: line_number,
+            amount: amount.to_string(),
+        };
+        let amount: i64 = amount.parse().map_err(|_| invalid())?;
+
+        match definition {
+            Definition::Alias(primitive, factor) => {
+                let amount = amount.checked_mul(*factor).ok_or_else(invalid)?;
+                Ok(Command::build(primitive, amount).unwrap())
+            }
+            Definition::Script(steps) => Ok(Command::Script(steps.clone(), amount)),
+        }
+    }
+}
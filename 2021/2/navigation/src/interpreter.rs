@@ -0,0 +1,147 @@
+use crate::command::{Command, Factor, Register, Step};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub horizontal: i64,
+    pub depth: i64,
+}
+
+/// Everything an interpreter keeps track of. Aim is only used by `forward` under [`Aimed`],
+/// but scripts can read and write it under either interpreter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub position: Position,
+    pub aim: i64,
+}
+
+impl State {
+    fn register(&mut self, register: Register) -> &mut i64 {
+        match register {
+            Register::Horizontal => &mut self.position.horizontal,
+            Register::Depth => &mut self.position.depth,
+            Register::Aim => &mut self.aim,
+        }
+    }
+
+    fn product(&mut self, factors: &[Factor], amount: i64) -> i64 {
+        factors
+            .iter()
+            .map(|f| match *f {
+                Factor::Constant(c) => c,
+                Factor::Amount => amount,
+                Factor::Register(r) => *self.register(r),
+            })
+            .product()
+    }
+
+    /// Runs the steps of a script in order.
+    pub fn run(&mut self, steps: &[Step], amount: i64) {
+        for step in steps {
+            match step {
+                Step::Add(r, factors) => *self.register(*r) += self.product(factors, amount),
+                Step::Subtract(r, factors) => *self.register(*r) -= self.product(factors, amount),
+                Step::Multiply(r, factors) => *self.register(*r) *= self.product(factors, amount),
+                Step::Set(r, factors) => *self.register(*r) = self.product(factors, amount),
+                Step::Swap(a, b) => {
+                    let value = *self.register(*a);
+                    *self.register(*a) = *self.register(*b);
+                    *self.register(*b) = value;
+                }
+            }
+        }
+    }
+}
+
+/// A way of moving the submarine in response to commands.
+pub trait Interpreter {
+    fn apply(&mut self, command: Command);
+    fn state(&self) -> State;
+
+    fn position(&self) -> Position {
+        self.state().position
+    }
+}
+
+/// `down` and `up` change the depth directly.
+#[derive(Default)]
+pub struct Direct {
+    state: State,
+}
+
+impl Interpreter for Direct {
+    fn apply(&mut self, command: Command) {
+        let position = &mut self.state.position;
+        match command {
+            Command::Forward(x) => position.horizontal += x,
+            Command::Down(x) => position.depth += x,
+            Command::Up(x) => position.depth -= x,
+            Command::Script(steps, x) => self.state.run(&steps, x),
+        }
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
+}
+
+/// `down` and `up` change the aim, and moving forward also dives by the aim times the
+/// distance.
+#[derive(Default)]
+pub struct Aimed {
+    state: State,
+}
+
+impl Interpreter for Aimed {
+    fn apply(&mut self, command: Command) {
+        let state = &mut self.state;
+        match command {
+            Command::Forward(x) => {
+                state.position.horizontal += x;
+                state.position.depth += state.aim * x;
+            }
+            Command::Down(x) => state.aim += x,
+            Command::Up(x) => state.aim -= x,
+            Command::Script(steps, x) => state.run(&steps, x),
+        }
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
+}
+
+/// Wraps another interpreter and remembers where each command left the submarine.
+pub struct Recorder<I> {
+    inner: I,
+    pub trajectory: Vec<Position>,
+}
+
+impl<I: Interpreter> Recorder<I> {
+    pub fn new(inner: I) -> Self {
+        Recorder {
+            trajectory: vec![inner.position()],
+            inner,
+        }
+    }
+}
+
+impl<I: Interpreter> Interpreter for Recorder<I> {
+    fn apply(&mut self, command: Command) {
+        self.inner.apply(command);
+        self.trajectory.push(self.inner.position());
+    }
+
+    fn state(&self) -> State {
+        self.inner.state()
+    }
+}
+
+impl<I: Interpreter + ?Sized> Interpreter for Box<I> {
+    fn apply(&mut self, command: Command) {
+        (**self).apply(command)
+    }
+
+    fn state(&self) -> State {
+        (**self).state()
+    }
+}
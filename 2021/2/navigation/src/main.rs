@@ -1,10 +1,19 @@
 use std::io::BufRead;
 
+mod command;
+mod interpreter;
+
+use command::CommandSet;
+use interpreter::{Aimed, Direct, Interpreter, Recorder};
+
 #[derive(Debug)]
 #[allow(dead_code)]
-enum Error {
+pub enum Error {
     IO(std::io::Error),
-    Parse(std::num::ParseIntError),
+    Malformed { line: usize },
+    UnknownCommand { line: usize, word: String },
+    InvalidAmount { line: usize, amount: String },
+    InvalidStep { line: usize, step: String },
 }
 
 impl From<std::io::Error> for Error {
@@ -13,39 +22,203 @@ impl From<std::io::Error> for Error {
     }
 }
 
-impl From<std::num::ParseIntError> for Error {
-    fn from(pie: std::num::ParseIntError) -> Self {
-        Error::Parse(pie)
-    }
-}
-
+// Usage: navigation FILE [--direct] [--commands CONFIG] [--trajectory]
+// --direct makes down and up change the depth rather than the aim, and CONFIG defines extra
+// command words or redefines the usual ones, see CommandSet::configure.
 fn main() -> Result<(), Error> {
     let args: Vec<_> = std::env::args().collect();
     let reader = std::io::BufReader::new(std::fs::File::open(&args[1])?);
 
-    let mut aim = 0;
-    let mut depth = 0;
-    let mut position = 0;
-    for l in reader.lines() {
-        let line = l?;
-        if line.starts_with("forward ") {
-            let x = rest(&line, "forward ")?;
-            position += x;
-            depth += aim * x;
-        } else if line.starts_with("down ") {
-            aim += rest(&line, "down ")?;
-        } else if line.starts_with("up ") {
-            aim -= rest(&line, "up ")?;
-        } else {
-            panic!("Unexpected directive");
+    let mut commands = CommandSet::default();
+    if let Some(i) = args.iter().position(|a| a == "--commands") {
+        commands.configure(&std::fs::read_to_string(&args[i + 1])?)?;
+    }
+
+    let interpreter: Box<dyn Interpreter> = if args.iter().any(|a| a == "--direct") {
+        Box::new(Direct::default())
+    } else {
+        Box::new(Aimed::default())
+    };
+    let recorder = navigate(&commands, reader, interpreter)?;
+
+    if args.iter().any(|a| a == "--trajectory") {
+        for p in &recorder.trajectory {
+            println!("{},{}", p.horizontal, p.depth);
         }
     }
 
-    println!("The position is '{}', depth is '{}', product is '{}'.", position, depth, position*depth);
+    let p = recorder.position();
+    println!(
+        "The position is '{}', depth is '{}', product is '{}'.",
+        p.horizontal,
+        p.depth,
+        p.horizontal * p.depth
+    );
     Ok(())
 }
 
-fn rest(input: &str, start: &str) -> Result<i32, std::num::ParseIntError> {
-    let rest = &input[start.len()..];
-    Ok(rest.parse()?)
-}
\ No newline at end of file
+/// Runs every line of a course through `interpreter`, recording where it goes.
+fn navigate<I: Interpreter>(
+    commands: &CommandSet,
+    reader: impl BufRead,
+    interpreter: I,
+) -> Result<Recorder<I>, Error> {
+    let mut recorder = Recorder::new(interpreter);
+    for (i, l) in reader.lines().enumerate() {
+        let line = l?;
+        if !line.trim().is_empty() {
+            recorder.apply(commands.parse(&line, i + 1)?);
+        }
+    }
+    Ok(recorder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interpreter::Position;
+
+    const EXAMPLE: &str = "forward 5
+down 5
+forward 8
+up 3
+down 8
+forward 2";
+
+    fn run<I: Interpreter>(
+        commands: &CommandSet,
+        course: &str,
+        interpreter: I,
+    ) -> Result<Position, Error> {
+        Ok(navigate(commands, course.as_bytes(), interpreter)?.position())
+    }
+
+    fn at(horizontal: i64, depth: i64) -> Position {
+        Position { horizontal, depth }
+    }
+
+    fn product(p: Position) -> i64 {
+        p.horizontal * p.depth
+    }
+
+    #[test]
+    fn example() -> Result<(), Error> {
+        let commands = CommandSet::default();
+        assert_eq!(150, product(run(&commands, EXAMPLE, Direct::default())?));
+        assert_eq!(900, product(run(&commands, EXAMPLE, Aimed::default())?));
+        Ok(())
+    }
+
+    #[test]
+    fn trajectory() -> Result<(), Error> {
+        let recorder = navigate(&CommandSet::default(), EXAMPLE.as_bytes(), Aimed::default())?;
+        let depths: Vec<_> = recorder.trajectory.iter().map(|p| p.depth).collect();
+        assert_eq!(vec![0, 0, 0, 40, 40, 40, 60], depths);
+        assert_eq!(at(15, 60), recorder.trajectory[6]);
+        Ok(())
+    }
+
+    #[test]
+    fn alias() -> Result<(), Error> {
+        let mut commands = CommandSet::default();
+        commands.configure("# Reverse gear\nback forward -1\n\nsink down 2")?;
+        let course = "forward 10\nsink 1\nback 4";
+        assert_eq!(at(6, 2), run(&commands, course, Direct::default())?);
+        assert_eq!(at(6, -8), run(&commands, course, Aimed::default())?);
+        Ok(())
+    }
+
+    #[test]
+    fn scripts() -> Result<(), Error> {
+        let mut commands = CommandSet::default();
+        commands.configure(
+            "turn: aim *= -1
+flip: swap aim depth
+level: aim = 0
+glide: horizontal += x; depth -= 2 * aim * x",
+        )?;
+
+        // Aim 3, turn it to -3, and forward 2 rises 6.
+        let course = "down 3\nturn 0\nforward 2";
+        assert_eq!(at(2, -6), run(&commands, course, Aimed::default())?);
+
+        // Direct never moves the aim itself, but scripts can.
+        let course = "down 7\nflip 0\nforward 1\nglide 2";
+        assert_eq!(at(3, -28), run(&commands, course, Direct::default())?);
+
+        let course = "down 5\nlevel 0\nforward 4";
+        assert_eq!(at(4, 0), run(&commands, course, Aimed::default())?);
+
+        // Redefining a built-in word changes it under every interpreter.
+        commands.configure("forward: horizontal += x * 10")?;
+        assert_eq!(
+            at(80, 0),
+            run(&commands, "down 5\nforward 8", Aimed::default())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn course_errors() {
+        let commands = CommandSet::default();
+        assert!(matches!(
+            run(&commands, "forward 1\nforward", Direct::default()),
+            Err(Error::Malformed { line: 2 })
+        ));
+        assert!(matches!(
+            run(&commands, "forward 1\n\ndown 2 3", Direct::default()),
+            Err(Error::Malformed { line: 3 })
+        ));
+        assert!(matches!(
+            run(&commands, "up 1\nturn 2", Direct::default()),
+            Err(Error::UnknownCommand { line: 2, word }) if word == "turn"
+        ));
+        assert!(matches!(
+            run(&commands, "down x", Direct::default()),
+            Err(Error::InvalidAmount { line: 1, amount }) if amount == "x"
+        ));
+    }
+
+    #[test]
+    fn overflow() {
+        let mut commands = CommandSet::default();
+        commands.configure("warp forward 4").unwrap();
+        assert!(matches!(
+            run(
+                &commands,
+                "warp 2\nwarp 9223372036854775807",
+                Direct::default()
+            ),
+            Err(Error::InvalidAmount { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn config_errors() {
+        let configure = |config: &str| CommandSet::default().configure(config);
+        assert!(matches!(
+            configure("# fine\nback forward"),
+            Err(Error::Malformed { line: 2 })
+        ));
+        assert!(matches!(
+            configure(": aim = 1"),
+            Err(Error::Malformed { line: 1 })
+        ));
+        assert!(matches!(
+            configure("back forward -1\nleap jump 2"),
+            Err(Error::UnknownCommand { line: 2, word }) if word == "jump"
+        ));
+        assert!(matches!(
+            configure("back forward minus"),
+            Err(Error::InvalidAmount { line: 1, amount }) if amount == "minus"
+        ));
+        assert!(matches!(
+            configure("\nturn: aim *= -1; speed += 1"),
+            Err(Error::InvalidStep { line: 2, step }) if step == "speed += 1"
+        ));
+        assert!(matches!(
+            configure("flip: swap aim"),
+            Err(Error::InvalidStep { line: 1, .. })
+        ));
+    }
+}